@@ -2,19 +2,32 @@ use super::chat;
 use super::config;
 use super::errors::*;
 use super::events;
+use super::intel;
 use super::intel::Intel;
 use super::notifications;
+use super::sde;
+use super::universe;
 
+use chrono::prelude::*;
+use chrono::Duration;
+use std::fs;
+use std::path;
 use std::sync::mpsc;
+use std::sync::Arc;
 use std::thread;
+use std::time;
 
-pub fn run(conf: config::Config) -> Result<()> {
+/// Systems offered when a name given on the command line is not found.
+const SUGGESTIONS: usize = 5;
+
+pub fn run(conf: config::Config, universe: Arc<universe::Universe>) -> Result<()> {
     let (tx, messages) = mpsc::channel();
     let watch_conf = conf.clone();
-    let mut intel = Intel::new(tx.clone());
-    let notifications = notifications::Notifications::new();
+    let watch_universe = universe.clone();
+    let mut intel = Intel::new(tx.clone(), &conf, universe);
+    let notifications = notifications::Notifications::new(&conf.notifications);
     thread::spawn(move || {
-        chat::watch(&watch_conf, tx).is_ok();
+        chat::watch(&watch_conf, &watch_universe, tx).is_ok();
     });
 
     loop {
@@ -22,6 +35,8 @@ pub fn run(conf: config::Config) -> Result<()> {
         match message {
             events::Event::PreviousMessage(message) => if message.is_local_channel() {
                 intel.location_message(message)?;
+            } else {
+                intel.sender(&message);
             },
             events::Event::NewMessage(message) => if message.is_local_channel() {
                 intel.location_message(message)?;
@@ -29,6 +44,378 @@ pub fn run(conf: config::Config) -> Result<()> {
                 intel.intel_message(message)?;
             },
             events::Event::IntelReport(message) => notifications.deliver(message)?,
+            events::Event::ConfigChanged(conf) => {
+                info!("Configuration reloaded");
+                intel.reconfigure(&conf);
+                notifications.reconfigure(&conf.notifications)?;
+            }
         };
     }
 }
+
+/// Feeds every message found in the chat logs through the intel pipeline as
+/// if it had just been received and prints the resulting reports.
+pub fn replay(conf: config::Config, universe: Arc<universe::Universe>) -> Result<()> {
+    let (tx, reports) = mpsc::channel();
+    let mut intel = Intel::new(tx, &conf, universe);
+
+    for message in chat::history(&conf)?.into_iter() {
+        let received_at = message.received_at;
+        if message.is_local_channel() {
+            intel.location_message(message)?;
+        } else {
+            intel.intel_message(message)?;
+        }
+
+        while let Ok(event) = reports.try_recv() {
+            if let events::Event::IntelReport(report) = event {
+                print_report(&received_at, &report);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn route(
+    universe: &universe::Universe,
+    from: &str,
+    to: &str,
+    options: &universe::RouteOptions,
+) -> Result<()> {
+    let source = find(universe, from)?;
+    let destination = find(universe, to)?;
+    let route = universe::route(universe, source, destination, options)
+        .chain_err(|| format!("no route from {} to {}", source.name, destination.name))?;
+
+    let mut path = route.source.name.clone();
+    for (system, hop) in route.systems.iter().skip(1).zip(route.hops.iter()) {
+        path.push_str(match *hop {
+            universe::Hop::Stargate => " > ",
+            universe::Hop::JumpBridge => " >> ",
+            universe::Hop::Wormhole => " ~> ",
+        });
+        path.push_str(&system.name);
+    }
+
+    println!("{} jumps: {}", route.distance, path);
+    Ok(())
+}
+
+pub fn add_wormhole(
+    conf: &config::Config,
+    universe: &universe::Universe,
+    from: &str,
+    to: &str,
+    hours: i64,
+) -> Result<()> {
+    let file = conf
+        .wormholes_path()
+        .chain_err(|| "no wormhole file configured")?;
+    let from = find(universe, from)?;
+    let to = find(universe, to)?;
+    let mut wormholes = universe::Wormholes::load(universe, &file)?;
+
+    wormholes.add(from, to, Utc::now() + Duration::hours(hours));
+    wormholes.save(&file)?;
+    println!("{} <-> {} for {} hours", from.name, to.name, hours);
+    Ok(())
+}
+
+pub fn remove_wormhole(
+    conf: &config::Config,
+    universe: &universe::Universe,
+    from: &str,
+    to: &str,
+) -> Result<()> {
+    let file = conf
+        .wormholes_path()
+        .chain_err(|| "no wormhole file configured")?;
+    let from = find(universe, from)?;
+    let to = find(universe, to)?;
+    let mut wormholes = universe::Wormholes::load(universe, &file)?;
+
+    if !wormholes.remove(from, to) {
+        bail!("no wormhole between {} and {}", from.name, to.name);
+    }
+    wormholes.save(&file)?;
+    Ok(())
+}
+
+pub fn list_wormholes(conf: &config::Config) -> Result<()> {
+    let now = Utc::now();
+    for hole in conf.routing.travel.wormholes.active() {
+        println!(
+            "{} <-> {} expires in {} minutes",
+            hole.from,
+            hole.to,
+            hole.expires_at.signed_duration_since(now).num_minutes()
+        );
+    }
+    Ok(())
+}
+
+pub fn parse(
+    conf: &config::Config,
+    universe: &universe::Universe,
+    location: &str,
+    line: &str,
+) -> Result<()> {
+    let location = find(universe, location)?;
+    let message = chat::Message {
+        received_at: Utc::now(),
+        listener: "eintel".to_string(),
+        channel: "eintel".to_string(),
+        sender: "eintel".to_string(),
+        message: line.to_string(),
+    };
+
+    let channel = config::Channel::new(&message.channel);
+    let roster = intel::Roster::default();
+    match intel::Message::new(message, location, conf, &channel, universe, &roster) {
+        Some(report) => println!("{:#?}", report),
+        None => println!("no intel found in '{}'", line),
+    }
+    Ok(())
+}
+
+/// Times the intel routing from `from` to every system: compiling the
+/// network, full routes, the first distance lookups, which build the distance
+/// table, and `rounds` of repeated ones.
+pub fn bench(
+    conf: &config::Config,
+    universe: &universe::Universe,
+    from: &str,
+    rounds: u32,
+) -> Result<()> {
+    let source = find(universe, from)?;
+    let systems = universe.systems();
+    let options = conf.routing.intel.clone();
+
+    let started = time::Instant::now();
+    universe::route(universe, source, source, &options);
+    report_timing("network", 1, started.elapsed());
+
+    let started = time::Instant::now();
+    let reachable = systems
+        .iter()
+        .filter_map(|system| universe::route(universe, source, system, &options))
+        .count();
+    report_timing("routes", systems.len(), started.elapsed());
+
+    let started = time::Instant::now();
+    for system in systems.iter() {
+        universe::distance(universe, source, system, &options);
+    }
+    report_timing("distances", systems.len(), started.elapsed());
+
+    let started = time::Instant::now();
+    for _ in 0..rounds {
+        for system in systems.iter() {
+            universe::distance(universe, source, system, &options);
+        }
+    }
+    report_timing("cached", systems.len() * rounds as usize, started.elapsed());
+
+    println!(
+        "{} of {} systems reachable from {}",
+        reachable,
+        systems.len(),
+        source.name
+    );
+    Ok(())
+}
+
+/// Builds the universe from the static data export at `sde` into `output`,
+/// adding the ship names found to `ships.txt` in `data` with
+/// `update_ships`.
+pub fn import(
+    sde: &path::Path,
+    data: &path::Path,
+    output: &path::Path,
+    build: Option<i64>,
+    wormhole_space: bool,
+    update_ships: bool,
+) -> Result<()> {
+    let ships_file = data.join("ships.txt");
+    let build = match build {
+        Some(build) => Some(build),
+        None => sde::build(sde)?,
+    };
+    let systems = sde::systems(sde, wormhole_space)?;
+    let mut ships = words(&ships_file)?;
+    let mut ship_types = vec![];
+    for ship in sde::ships(sde)?.into_iter() {
+        let name = ship.name.to_uppercase();
+        if !ships.contains(&name) {
+            ships.push(name.clone());
+        }
+        ship_types.push((name, ship));
+    }
+    let classes_file = data.join("ship_classes.txt");
+    let classes = fs::read_to_string(&classes_file)
+        .chain_err(|| format!("unable to read {}", classes_file.display()))?;
+    ship_types.extend(
+        universe::ShipType::parse(&classes)
+            .chain_err(|| format!("invalid ship classes {}", classes_file.display()))?,
+    );
+    let stop_words = words(&data.join("stop_words.txt"))?;
+
+    if update_ships {
+        fs::write(&ships_file, ships.join("\n") + "\n")
+            .chain_err(|| format!("unable to write {}", ships_file.display()))?;
+        println!(
+            "{} ship names written to {}",
+            ships.len(),
+            ships_file.display()
+        );
+    }
+
+    let universe = universe::Universe::new(systems, ships, ship_types, stop_words, build);
+    universe.save(output)?;
+    println!("{} systems written to {}", universe.len(), output.display());
+    Ok(())
+}
+
+fn words(file: &path::Path) -> Result<Vec<String>> {
+    Ok(fs::read_to_string(file)
+        .chain_err(|| format!("unable to read {}", file.display()))?
+        .lines()
+        .map(|word| word.trim().to_string())
+        .filter(|word| !word.is_empty())
+        .collect())
+}
+
+fn find<'a>(universe: &'a universe::Universe, name: &str) -> Result<&'a universe::System> {
+    if let Some(system) = universe.find(name) {
+        return Ok(system);
+    }
+
+    let suggestions = universe
+        .resolve(name, None)
+        .iter()
+        .take(SUGGESTIONS)
+        .map(|candidate| candidate.system.name.clone())
+        .collect::<Vec<_>>();
+    if suggestions.is_empty() {
+        bail!("no such system {}", name)
+    }
+    bail!(
+        "no such system {}, did you mean {}?",
+        name,
+        suggestions.join(", ")
+    )
+}
+
+fn report_timing(what: &str, count: usize, elapsed: time::Duration) {
+    let micros = elapsed.as_secs() as f64 * 1e6 + elapsed.subsec_nanos() as f64 / 1e3;
+    println!(
+        "{:>8} {:<16} {:>10.1} ms {:>8.2} µs each",
+        count,
+        what,
+        micros / 1e3,
+        micros / count.max(1) as f64
+    );
+}
+
+fn print_report(received_at: &DateTime<Utc>, report: &intel::Message) {
+    println!(
+        "{} [{}] {} > {} => {:?}",
+        received_at.format("%Y.%m.%d %H:%M:%S"),
+        report.player,
+        report.sender,
+        report.message,
+        report.threat_assement
+    );
+    if let Some(band) = report.threat_assement.band() {
+        println!("    band: {} ({:?})", band.name, band.level);
+    }
+    match notifications::Notification::from(report.clone()) {
+        notifications::Notification::Sound(text) => println!("    says \"{}\"", text),
+        notifications::Notification::Desktop(text) => println!("    shows \"{}\"", text),
+        notifications::Notification::None => {}
+    }
+    if let Some(hostiles) = report.hostiles {
+        println!("    hostiles: {}", hostiles);
+    }
+    if !report.ships.is_empty() {
+        let ships = report
+            .ships
+            .iter()
+            .map(|ship| {
+                let kind = [&ship.class, &ship.hull]
+                    .iter()
+                    .filter(|kind| !kind.is_empty())
+                    .map(|kind| kind.as_str())
+                    .collect::<Vec<_>>();
+                match kind.len() {
+                    0 => ship.name.clone(),
+                    _ => format!("{} ({})", ship.name, kind.join(", ")),
+                }
+            })
+            .collect::<Vec<_>>();
+        println!("    ships: {}", ships.join(", "));
+    }
+    if let Some(ref approach) = report.approach {
+        println!(
+            "    approaching from {} ({} jumps) to {} ({} jumps)",
+            approach.from.name, approach.jumps_before, approach.to.name, approach.jumps
+        );
+    }
+    match report.place {
+        Some(intel::Place::Gate(ref gate)) if report.on_route => {
+            println!("    on the {} gate, on the route", gate.name)
+        }
+        Some(intel::Place::Gate(ref gate)) => println!("    on the {} gate", gate.name),
+        Some(intel::Place::Structure(ref kind)) => println!("    at the {}", kind),
+        None => {}
+    }
+    if let Some(ref movement) = report.movement {
+        match movement.from {
+            Some(ref from) => println!("    moving from {} to {}", from.name, movement.to.name),
+            None => println!("    moving to {}", movement.to.name),
+        }
+    }
+    if !report.involved_players.is_empty() {
+        let players = report
+            .involved_players
+            .iter()
+            .map(|player| match player.id {
+                Some(ref id) => format!(
+                    "{} ({:?} to {}, {:.0}%)",
+                    player.name,
+                    player.evidence,
+                    id,
+                    player.confidence * 100.0
+                ),
+                None => format!(
+                    "{} ({:?}, {:.0}%)",
+                    player.name,
+                    player.evidence,
+                    player.confidence * 100.0
+                ),
+            })
+            .collect::<Vec<_>>();
+        println!("    players: {}", players.join(", "));
+    }
+    for sighting in report.sightings.iter() {
+        match (sighting.previous, report.status) {
+            (Some(previous), intel::Status::Requested) => {
+                println!("    {}: still {:?}", sighting.system.name, previous)
+            }
+            (Some(previous), status) if previous != status => {
+                println!(
+                    "    {}: {:?} -> {:?}",
+                    sighting.system.name, previous, status
+                )
+            }
+            (None, status) => println!("    {}: {:?}", sighting.system.name, status),
+            (Some(_), _) => {}
+        }
+    }
+    for sighting in report.sightings.iter().skip(1) {
+        println!(
+            "    also {} => {:?}",
+            sighting.system.name, sighting.threat_assement
+        );
+    }
+}
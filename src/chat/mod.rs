@@ -1,6 +1,7 @@
 use super::config;
 use super::errors::*;
 use super::events::Event;
+use super::universe;
 
 pub mod channel;
 mod header;
@@ -16,12 +17,12 @@ use std::time;
 
 lazy_static! {
     static ref message_pattern: regex::Regex = {
-        regex::Regex::new(r"^\[ (\d{4}\.\d{2}\.\d{2} \d{2}:\d{2}:\d{2}) \] (.+) > (.*)")
+        regex::Regex::new(r"^\[ (\d{4}\.\d{2}\.\d{2} \d{2}:\d{2}:\d{2}) \] (.+?) > (.*)")
             .expect("must compile")
     };
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Message {
     pub received_at: DateTime<Utc>,
     pub listener: String,
@@ -54,20 +55,16 @@ impl Message {
     }
 }
 
-pub fn watch(conf: &config::Config, chan: mpsc::Sender<Event>) -> Result<()> {
-    let mut channels = HashSet::new();
-    let mut messages: Vec<Message> = vec![];
+pub fn watch(
+    conf: &config::Config,
+    universe: &universe::Universe,
+    chan: mpsc::Sender<Event>,
+) -> Result<()> {
+    let mut conf = conf.clone();
+    let (mut channels, messages) = restore(&conf)?;
 
-    for mut channel in restore_channels(conf)?.into_iter() {
-        messages.append(&mut channel.messages()?);
-        channels.insert(channel);
-    }
-
-    messages.sort_by(|first, last| first.received_at.cmp(&last.received_at));
     for message in messages.into_iter() {
-        if is_relevant_message(&message, &conf) {
-            chan.send(Event::PreviousMessage(message))?;
-        }
+        chan.send(Event::PreviousMessage(message))?;
     }
 
     let (tx, fs_events) = mpsc::channel();
@@ -75,9 +72,28 @@ pub fn watch(conf: &config::Config, chan: mpsc::Sender<Event>) -> Result<()> {
         notify::Watcher::new(tx, time::Duration::from_millis(200)).unwrap();
 
     watcher.watch(conf.chat_logs.clone(), notify::RecursiveMode::Recursive)?;
+    watch_files(&mut watcher, &conf)?;
+
     loop {
         match fs_events.recv()? {
+            notify::DebouncedEvent::Create(ref path)
+            | notify::DebouncedEvent::Write(ref path)
+            | notify::DebouncedEvent::Chmod(ref path)
+            | notify::DebouncedEvent::Rename(_, ref path)
+                if conf.depends_on(path) =>
+            {
+                match reload(&conf, universe, &mut channels, &chan) {
+                    Ok(new_conf) => {
+                        conf = new_conf;
+                        watch_files(&mut watcher, &conf)?;
+                    }
+                    Err(error) => error!("unable to reload configuration: {}", error),
+                }
+            }
             notify::DebouncedEvent::Write(path) | notify::DebouncedEvent::Chmod(path) => {
+                if relevant_channel_path(path.clone(), &conf.channels).is_none() {
+                    continue;
+                }
                 let mut channel = figureout_channel(channel::Channel::from(&path)?, &mut channels);
                 for message in channel.messages()?.into_iter() {
                     if is_relevant_message(&message, &conf) {
@@ -91,6 +107,75 @@ pub fn watch(conf: &config::Config, chan: mpsc::Sender<Event>) -> Result<()> {
     }
 }
 
+/// Editors tend to replace files instead of writing to them, so the
+/// directories of the configuration files are watched rather than the files.
+fn watch_files(watcher: &mut notify::RecommendedWatcher, conf: &config::Config) -> Result<()> {
+    let mut dirs = conf
+        .files()
+        .iter()
+        .filter_map(|file| file.parent().map(|dir| dir.to_path_buf()))
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs.dedup();
+
+    for dir in dirs.iter().filter(|dir| !dir.starts_with(&conf.chat_logs)) {
+        watcher.watch(dir, notify::RecursiveMode::NonRecursive)?;
+    }
+    Ok(())
+}
+
+/// Re-reads the configuration and replays the history of channels that were
+/// not watched before, so locations of newly added players are known.
+fn reload(
+    conf: &config::Config,
+    universe: &universe::Universe,
+    channels: &mut HashSet<channel::Channel>,
+    chan: &mpsc::Sender<Event>,
+) -> Result<config::Config> {
+    let conf = conf.reload(universe)?;
+    let mut messages: Vec<Message> = vec![];
+
+    for mut channel in restore_channels(&conf)?.into_iter() {
+        if !channels.contains(&channel) {
+            info!(
+                "Watching {} for {}",
+                channel.header.name, channel.header.listener
+            );
+            messages.append(&mut channel.messages()?);
+            channels.insert(channel);
+        }
+    }
+
+    messages.retain(|message| is_relevant_message(message, &conf));
+    messages.sort_by(|first, last| first.received_at.cmp(&last.received_at));
+    for message in messages.into_iter() {
+        chan.send(Event::PreviousMessage(message))?;
+    }
+
+    chan.send(Event::ConfigChanged(conf.clone()))?;
+    Ok(conf)
+}
+
+/// Relevant messages already present in the chat logs, oldest first.
+pub fn history(conf: &config::Config) -> Result<Vec<Message>> {
+    let (_, messages) = restore(conf)?;
+    Ok(messages)
+}
+
+fn restore(conf: &config::Config) -> Result<(HashSet<channel::Channel>, Vec<Message>)> {
+    let mut channels = HashSet::new();
+    let mut messages: Vec<Message> = vec![];
+
+    for mut channel in restore_channels(conf)?.into_iter() {
+        messages.append(&mut channel.messages()?);
+        channels.insert(channel);
+    }
+
+    messages.retain(|message| is_relevant_message(message, conf));
+    messages.sort_by(|first, last| first.received_at.cmp(&last.received_at));
+    Ok((channels, messages))
+}
+
 fn is_relevant_message(message: &Message, conf: &config::Config) -> bool {
     conf.players.iter().any(|name| &message.listener == name)
         && (message.channel == "Local" && message.sender == "EVE System")
@@ -130,16 +215,13 @@ fn restore_channels(conf: &config::Config) -> Result<HashSet<channel::Channel>>
     Ok(channels)
 }
 
-fn relevant_channel_path(path: path::PathBuf, channels: &Vec<String>) -> Option<path::PathBuf> {
-    let valid = channels.iter().any({
-        |name| {
-            path.file_stem()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .starts_with(name)
-        }
-    });
+fn relevant_channel_path(
+    path: path::PathBuf,
+    channels: &Vec<config::Channel>,
+) -> Option<path::PathBuf> {
+    let valid = channels
+        .iter()
+        .any(|channel| channel.matches(path.file_stem().unwrap().to_str().unwrap()));
 
     if valid {
         Some(path)
@@ -0,0 +1,336 @@
+use super::config;
+use super::errors::*;
+use super::universe;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use log;
+use std::env;
+use std::path;
+use std::str::FromStr;
+
+const DEFAULT_WORMHOLE_HOURS: i64 = 16;
+const DEFAULT_BENCH_ROUNDS: u32 = 10;
+
+pub enum Command {
+    Watch,
+    Route(
+        String,
+        String,
+        universe::Avoidance,
+        Option<universe::Preference>,
+    ),
+    Parse(String, String),
+    Replay(path::PathBuf),
+    Wormhole(WormholeCommand),
+    Bench(String, u32),
+    Import {
+        sde: path::PathBuf,
+        data: path::PathBuf,
+        output: path::PathBuf,
+        build: Option<i64>,
+        wormhole_space: bool,
+        update_ships: bool,
+    },
+}
+
+pub enum WormholeCommand {
+    Add(String, String, i64),
+    Remove(String, String),
+    List,
+}
+
+pub struct Cli {
+    matches: ArgMatches<'static>,
+}
+
+impl Cli {
+    pub fn new() -> Cli {
+        Cli {
+            matches: app().get_matches(),
+        }
+    }
+
+    pub fn command(&self) -> Command {
+        match self.matches.subcommand() {
+            ("route", Some(args)) => Command::Route(
+                args.value_of("from").unwrap_or_default().to_string(),
+                args.value_of("to").unwrap_or_default().to_string(),
+                universe::Avoidance {
+                    systems: values(args, "avoid"),
+                    constellations: values(args, "avoid-constellation"),
+                    regions: values(args, "avoid-region"),
+                },
+                match args.value_of("prefer") {
+                    Some("shortest") => Some(universe::Preference::Shortest),
+                    Some("safer") => Some(universe::Preference::Safer),
+                    Some("less-secure") => Some(universe::Preference::LessSecure),
+                    _ => None,
+                },
+            ),
+            ("parse", Some(args)) => Command::Parse(
+                args.value_of("location").unwrap_or_default().to_string(),
+                args.value_of("line").unwrap_or_default().to_string(),
+            ),
+            ("replay", Some(args)) => Command::Replay(path::PathBuf::from(
+                args.value_of_os("logdir").unwrap_or_default(),
+            )),
+            ("wormhole", Some(args)) => Command::Wormhole(match args.subcommand() {
+                ("add", Some(args)) => WormholeCommand::Add(
+                    args.value_of("from").unwrap_or_default().to_string(),
+                    args.value_of("to").unwrap_or_default().to_string(),
+                    args.value_of("hours")
+                        .and_then(|hours| hours.parse().ok())
+                        .unwrap_or(DEFAULT_WORMHOLE_HOURS),
+                ),
+                ("remove", Some(args)) => WormholeCommand::Remove(
+                    args.value_of("from").unwrap_or_default().to_string(),
+                    args.value_of("to").unwrap_or_default().to_string(),
+                ),
+                _ => WormholeCommand::List,
+            }),
+            ("bench", Some(args)) => Command::Bench(
+                args.value_of("from").unwrap_or_default().to_string(),
+                args.value_of("rounds")
+                    .and_then(|rounds| rounds.parse().ok())
+                    .unwrap_or(DEFAULT_BENCH_ROUNDS),
+            ),
+            ("import", Some(args)) => Command::Import {
+                sde: path::PathBuf::from(args.value_of_os("sde").unwrap_or_default()),
+                data: path::PathBuf::from(args.value_of_os("data").unwrap_or_default()),
+                output: path::PathBuf::from(args.value_of_os("output").unwrap_or_default()),
+                build: args.value_of("build").and_then(|build| build.parse().ok()),
+                wormhole_space: args.is_present("wormhole-space"),
+                update_ships: args.is_present("update-ships"),
+            },
+            _ => Command::Watch,
+        }
+    }
+
+    pub fn log_level(&self) -> Result<log::LevelFilter> {
+        let level = self.matches.value_of("log-level").unwrap_or("debug");
+        log::LevelFilter::from_str(level)
+            .ok()
+            .chain_err(|| format!("invalid log level '{}'", level))
+    }
+
+    /// Loads the configuration file and applies the command line overrides
+    /// on top of it.
+    pub fn config(&self) -> Result<config::Config> {
+        let file = self.matches.value_of_os("config").map(path::Path::new);
+        let overrides = config::Overrides {
+            players: self
+                .matches
+                .values_of("player")
+                .map(|players| players.map(|player| player.to_string()).collect()),
+            channels: self
+                .matches
+                .values_of("channel")
+                .map(|channels| channels.map(|channel| channel.to_string()).collect()),
+            chat_logs: self
+                .matches
+                .value_of_os("chat-logs")
+                .map(path::PathBuf::from),
+            universe: self
+                .matches
+                .value_of_os("universe")
+                .map(|file| env::current_dir().unwrap_or_default().join(file)),
+        };
+
+        Ok(config::Config::load(file)?.with_overrides(overrides))
+    }
+}
+
+fn values(args: &ArgMatches, name: &str) -> Vec<String> {
+    args.values_of(name)
+        .map(|values| values.map(|value| value.to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn app() -> App<'static, 'static> {
+    App::new("eintel")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("EVE Online intel channel monitor")
+        .setting(AppSettings::VersionlessSubcommands)
+        .arg(
+            Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .value_name("FILE")
+                .help("Configuration file to use instead of the default search path")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("chat-logs")
+                .long("chat-logs")
+                .value_name("DIR")
+                .help("EVE chat log directory")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("player")
+                .short("p")
+                .long("player")
+                .value_name("NAME")
+                .help("Character to track, replaces the configured players")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("channel")
+                .long("channel")
+                .value_name("NAME")
+                .help("Intel channel to watch, replaces the configured channels")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("log-level")
+                .short("l")
+                .long("log-level")
+                .value_name("LEVEL")
+                .possible_values(&["off", "error", "warn", "info", "debug", "trace"])
+                .default_value("debug")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("universe")
+                .short("u")
+                .long("universe")
+                .value_name("FILE")
+                .help("Universe data file to use instead of the built in one")
+                .takes_value(true),
+        )
+        .subcommand(SubCommand::with_name("watch").about("Watch the chat logs for intel (default)"))
+        .subcommand(
+            SubCommand::with_name("route")
+                .about("Print the route between two systems")
+                .arg(Arg::with_name("from").required(true))
+                .arg(Arg::with_name("to").required(true))
+                .arg(avoid_arg("avoid", "SYSTEM"))
+                .arg(avoid_arg("avoid-constellation", "CONSTELLATION"))
+                .arg(avoid_arg("avoid-region", "REGION"))
+                .arg(
+                    Arg::with_name("prefer")
+                        .long("prefer")
+                        .value_name("PREFERENCE")
+                        .help("Route preference, defaults to the configured one")
+                        .possible_values(&["shortest", "safer", "less-secure"])
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("parse")
+                .about("Parse a single intel line as seen from a system")
+                .arg(
+                    Arg::with_name("location")
+                        .long("from")
+                        .value_name("SYSTEM")
+                        .help("System the listener is in")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(Arg::with_name("line").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("replay")
+                .about("Run the intel pipeline over an existing chat log directory")
+                .arg(Arg::with_name("logdir").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("wormhole")
+                .about("Manage temporary wormhole connections")
+                .subcommand(
+                    SubCommand::with_name("add")
+                        .about("Add a connection between two systems")
+                        .arg(Arg::with_name("from").required(true))
+                        .arg(Arg::with_name("to").required(true))
+                        .arg(
+                            Arg::with_name("hours")
+                                .long("hours")
+                                .value_name("HOURS")
+                                .help("Hours until the connection collapses [default: 16]")
+                                .validator(positive)
+                                .takes_value(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("remove")
+                        .about("Remove the connection between two systems")
+                        .arg(Arg::with_name("from").required(true))
+                        .arg(Arg::with_name("to").required(true)),
+                )
+                .subcommand(SubCommand::with_name("list").about("List active connections")),
+        )
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about("Time routing from a system to every other system")
+                .arg(Arg::with_name("from").required(true))
+                .arg(
+                    Arg::with_name("rounds")
+                        .long("rounds")
+                        .value_name("N")
+                        .help("Times every distance is looked up again [default: 10]")
+                        .validator(positive)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Build the universe from an unpacked static data export")
+                .arg(Arg::with_name("sde").required(true))
+                .arg(
+                    Arg::with_name("data")
+                        .long("data")
+                        .value_name("DIR")
+                        .help("Directory with ships.txt, ship_classes.txt and stop_words.txt")
+                        .default_value("data")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .default_value("universe.bson")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("build")
+                        .long("build")
+                        .value_name("NUMBER")
+                        .help("Build of the static data export, when it does not name it itself")
+                        .validator(positive)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("wormhole-space")
+                        .long("wormhole-space")
+                        .help("Also import Thera and J-space, to route through wormholes to them"),
+                )
+                .arg(
+                    Arg::with_name("update-ships")
+                        .long("update-ships")
+                        .help("Also add the ship names found to ships.txt in --data"),
+                ),
+        )
+}
+
+/// Accepts whole numbers above zero.
+fn positive(value: String) -> ::std::result::Result<(), String> {
+    match value.parse::<u32>() {
+        Ok(number) if number > 0 => Ok(()),
+        _ => Err(format!("expected a whole number above zero, got {}", value)),
+    }
+}
+
+fn avoid_arg(name: &'static str, value_name: &'static str) -> Arg<'static, 'static> {
+    Arg::with_name(name)
+        .long(name)
+        .value_name(value_name)
+        .help("Route around it, in addition to the configured avoidance list")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+}
@@ -0,0 +1,150 @@
+use serde::de;
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trust {
+    Low,
+    Normal,
+    High,
+}
+
+impl Default for Trust {
+    fn default() -> Trust {
+        Trust::Normal
+    }
+}
+
+/// An intel channel and how much its reports are to be believed.
+///
+/// Configured either as a plain channel name or as a table with `name`,
+/// `trust`, `players` (empty means every player) and `audio`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Channel {
+    pub name: String,
+    pub trust: Trust,
+    pub players: Vec<String>,
+    pub audio: bool,
+}
+
+impl Channel {
+    pub fn new(name: &str) -> Channel {
+        Channel {
+            name: name.to_string(),
+            trust: Trust::default(),
+            players: vec![],
+            audio: true,
+        }
+    }
+
+    /// Channel log files and headers are matched by name prefix.
+    pub fn matches(&self, name: &str) -> bool {
+        name.starts_with(&self.name)
+    }
+
+    pub fn applies_to(&self, player: &str) -> bool {
+        self.players.is_empty() || self.players.iter().any(|name| name == player)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChannelTable {
+    name: String,
+    #[serde(default)]
+    trust: Trust,
+    #[serde(default)]
+    players: Vec<String>,
+    #[serde(default = "enabled")]
+    audio: bool,
+}
+
+impl<'de> Deserialize<'de> for Channel {
+    fn deserialize<D>(deserializer: D) -> Result<Channel, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ChannelVisitor)
+    }
+}
+
+/// Takes a channel either from its name or from a table, whose errors, like
+/// unknown keys, are reported as they are.
+struct ChannelVisitor;
+
+impl<'de> de::Visitor<'de> for ChannelVisitor {
+    type Value = Channel;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a channel name or a table with a name")
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<Channel, E> {
+        Ok(Channel::new(name))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Channel, A::Error> {
+        let table = ChannelTable::deserialize(de::value::MapAccessDeserializer::new(map))
+            .map_err(|error| de::Error::custom(format!("invalid channel: {}", error)))?;
+        Ok(Channel {
+            name: table.name,
+            trust: table.trust,
+            players: table.players,
+            audio: table.audio,
+        })
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use toml;
+
+    #[derive(Deserialize)]
+    struct Channels {
+        channels: Vec<Channel>,
+    }
+
+    fn parse(text: &str) -> Result<Vec<Channel>, toml::de::Error> {
+        toml::from_str::<Channels>(text).map(|parsed| parsed.channels)
+    }
+
+    #[test]
+    fn reads_names() {
+        let channels = parse(r#"channels = ["Home Intel"]"#).unwrap();
+
+        assert_eq!(channels, vec![Channel::new("Home Intel")]);
+    }
+
+    #[test]
+    fn reads_tables() {
+        let channels = parse(
+            r#"
+            [[channels]]
+            name = "Delve Intel"
+            trust = "low"
+            audio = false
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(channels[0].name, "Delve Intel");
+        assert_eq!(channels[0].trust, Trust::Low);
+        assert!(channels[0].players.is_empty());
+        assert!(!channels[0].audio);
+    }
+
+    #[test]
+    fn reports_unknown_keys_in_tables() {
+        let error = parse(r#"channels = [{ name = "Delve Intel", trsut = "low" }]"#)
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("unknown field `trsut`"), "{}", error);
+    }
+}
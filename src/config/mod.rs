@@ -1,35 +1,382 @@
 use super::errors::*;
+use super::universe;
+
+use serde_json;
+use toml;
 
 use std::env;
+use std::fs;
 use std::path;
 
-#[derive(Clone)]
+mod channel;
+mod threat;
+pub use self::channel::{Channel, Trust};
+pub use self::threat::{ThreatBand, ThreatBands, ThreatLevel, ThreatSettings};
+
+const LOCAL_CHANNEL: &'static str = "Local";
+const CONFIG_FILES: [&'static str; 2] = ["config.toml", "config.json"];
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
-    pub channels: Vec<String>,
+    #[serde(default)]
+    pub channels: Vec<Channel>,
+    #[serde(default)]
     pub players: Vec<String>,
+    #[serde(default = "default_chat_logs")]
     pub chat_logs: path::PathBuf,
+    pub universe: Option<path::PathBuf>,
+    /// Files with shorthands for system names, read at start.
+    #[serde(default)]
+    pub aliases: Vec<path::PathBuf>,
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+    #[serde(default)]
+    pub threat: ThreatSettings,
+    #[serde(default)]
+    pub routing: RoutingSettings,
+    #[serde(skip)]
+    pub source: Option<path::PathBuf>,
+    #[serde(skip)]
+    pub overrides: Overrides,
+}
+
+/// Settings given on the command line, kept so they survive a reload of the
+/// configuration file.
+#[derive(Clone, Debug, Default)]
+pub struct Overrides {
+    pub players: Option<Vec<String>>,
+    pub channels: Option<Vec<String>>,
+    pub chat_logs: Option<path::PathBuf>,
+    pub universe: Option<path::PathBuf>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationSettings {
+    #[serde(default = "enabled")]
+    pub audio: bool,
+    #[serde(default = "enabled")]
+    pub desktop: bool,
+    #[serde(default = "default_voice")]
+    pub voice: String,
+    /// Command answers to status requests are piped into, to copy them.
+    pub clipboard: Option<String>,
+}
+
+/// Route options used to measure how far a reported hostile is (`intel`) and
+/// to plan our own travel (`travel`).
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoutingSettings {
+    #[serde(default)]
+    pub intel: universe::RouteOptions,
+    #[serde(default)]
+    pub travel: universe::RouteOptions,
+    pub jump_bridges: Option<path::PathBuf>,
+    pub wormholes: Option<path::PathBuf>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> NotificationSettings {
+        NotificationSettings {
+            audio: true,
+            desktop: true,
+            voice: default_voice(),
+            clipboard: None,
+        }
+    }
 }
 
 impl Config {
     pub fn default() -> Result<Config> {
         Ok({
             Config {
-                channels: vec!["Local".to_string()],
+                channels: vec![Channel::new(LOCAL_CHANNEL)],
                 players: vec![],
                 chat_logs: env::home_dir()
                     .chain_err(|| "chat log directory not found")?
                     .join("Documents/EVE/logs/Chatlogs"),
+                universe: None,
+                aliases: vec![],
+                notifications: NotificationSettings::default(),
+                threat: ThreatSettings::default(),
+                routing: RoutingSettings::default(),
+                source: None,
+                overrides: Overrides::default(),
             }
         })
     }
 
+    /// Loads `file` or, when not given, the first configuration file found
+    /// in `search_path`, falling back to the defaults when there is none.
+    pub fn load(file: Option<&path::Path>) -> Result<Config> {
+        if let Some(file) = file {
+            return Config::from_file(file);
+        }
+
+        let candidates = search_path();
+        match candidates.iter().find(|path| path.is_file()) {
+            Some(path) => Config::from_file(path),
+            None => {
+                warn!(
+                    "no configuration file found in {:?}, using defaults",
+                    candidates
+                );
+                Config::default()
+            }
+        }
+    }
+
+    pub fn from_file(path: &path::Path) -> Result<Config> {
+        let contents = fs::read_to_string(path)
+            .chain_err(|| format!("unable to read config file {}", path.display()))?;
+
+        let mut conf: Config = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&contents)
+                .chain_err(|| format!("invalid config file {}", path.display()))?,
+            _ => toml::from_str(&contents)
+                .chain_err(|| format!("invalid config file {}", path.display()))?,
+        };
+
+        info!("Loaded configuration from {}", path.display());
+        conf.source = Some(fs::canonicalize(path)?);
+        Ok(conf)
+    }
+
+    /// Reads the configuration file again, keeping the command line
+    /// overrides.
+    pub fn reload(&self, universe: &universe::Universe) -> Result<Config> {
+        let source = self
+            .source
+            .as_ref()
+            .ok_or("configuration was not loaded from a file")?;
+        Config::from_file(source)?
+            .with_overrides(self.overrides.clone())
+            .resolve(universe)?
+            .validate()
+    }
+
+    /// The configuration file and the data files it refers to, as file
+    /// events name them.
+    pub fn files(&self) -> Vec<path::PathBuf> {
+        let mut files = vec![];
+        files.extend(self.source.clone());
+        files.extend(self.jump_bridges_path().map(|file| canonical(&file)));
+        files.extend(self.wormholes_path().map(|file| canonical(&file)));
+        files
+    }
+
+    pub fn depends_on(&self, path: &path::Path) -> bool {
+        let path = canonical(path);
+        self.files().iter().any(|file| *file == path)
+    }
+
+    pub fn with_overrides(mut self, overrides: Overrides) -> Config {
+        if let Some(ref players) = overrides.players {
+            self.players.clear();
+            for player in players {
+                self = self.player(player);
+            }
+        }
+
+        if let Some(ref channels) = overrides.channels {
+            self.channels.clear();
+            for channel in channels {
+                self = self.channel(channel);
+            }
+        }
+
+        if let Some(ref chat_logs) = overrides.chat_logs {
+            self.chat_logs = chat_logs.clone();
+        }
+
+        if let Some(ref universe) = overrides.universe {
+            self.universe = Some(universe.clone());
+        }
+
+        self.overrides = overrides;
+        self
+    }
+
     pub fn player(mut self, player: &str) -> Config {
         self.players.push(player.to_string());
         self
     }
 
     pub fn channel(mut self, channel: &str) -> Config {
-        self.channels.push(channel.to_string());
+        self.channels.push(Channel::new(channel));
         self
     }
+
+    pub fn find_channel(&self, name: &str) -> Option<&Channel> {
+        self.channels.iter().find(|channel| channel.matches(name))
+    }
+
+    /// Loads the data files the configuration refers to. Relative paths are
+    /// taken relative to the configuration file.
+    pub fn resolve(mut self, universe: &universe::Universe) -> Result<Config> {
+        if let Some(file) = self.jump_bridges_path() {
+            let bridges = universe::JumpBridges::load(universe, &file)?;
+            info!("Loaded {} jump bridges", bridges.len());
+            self.routing.intel.bridges = bridges.clone();
+            self.routing.travel.bridges = bridges;
+        }
+
+        if let Some(file) = self.wormholes_path() {
+            let wormholes = universe::Wormholes::load(universe, &file)?;
+            info!("Loaded {} wormhole connections", wormholes.active().len());
+            self.routing.intel.wormholes = wormholes.clone();
+            self.routing.travel.wormholes = wormholes;
+        }
+        Ok(self)
+    }
+
+    /// The universe file replacing the built in universe, if any.
+    pub fn universe_path(&self) -> Option<path::PathBuf> {
+        self.universe.as_ref().map(|file| self.relative_path(file))
+    }
+
+    pub fn alias_paths(&self) -> Vec<path::PathBuf> {
+        self.aliases
+            .iter()
+            .map(|file| self.relative_path(file))
+            .collect()
+    }
+
+    pub fn jump_bridges_path(&self) -> Option<path::PathBuf> {
+        self.routing
+            .jump_bridges
+            .as_ref()
+            .map(|file| self.relative_path(file))
+    }
+
+    pub fn wormholes_path(&self) -> Option<path::PathBuf> {
+        self.routing
+            .wormholes
+            .as_ref()
+            .map(|file| self.relative_path(file))
+    }
+
+    fn relative_path(&self, file: &path::Path) -> path::PathBuf {
+        match self.source.as_ref().and_then(|source| source.parent()) {
+            Some(dir) => dir.join(file),
+            None => file.to_path_buf(),
+        }
+    }
+
+    pub fn validate(mut self) -> Result<Config> {
+        if !self.chat_logs.is_dir() {
+            bail!(
+                "chat log directory {} does not exist",
+                self.chat_logs.display()
+            );
+        }
+
+        self.threat.validate()?;
+
+        if !self
+            .channels
+            .iter()
+            .any(|channel| channel.name == LOCAL_CHANNEL)
+        {
+            self.channels.insert(0, Channel::new(LOCAL_CHANNEL));
+        }
+
+        Ok(self)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/eintel/config.{toml,json}`, then `~/.config/eintel/`.
+pub fn search_path() -> Vec<path::PathBuf> {
+    let mut dirs = vec![];
+
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        dirs.push(path::PathBuf::from(dir).join("eintel"));
+    }
+    if let Some(home) = env::home_dir() {
+        dirs.push(home.join(".config").join("eintel"));
+    }
+
+    dirs.iter()
+        .flat_map(|dir| CONFIG_FILES.iter().map(move |file| dir.join(file)))
+        .collect()
+}
+
+fn default_chat_logs() -> path::PathBuf {
+    env::home_dir()
+        .unwrap_or_default()
+        .join("Documents/EVE/logs/Chatlogs")
+}
+
+fn default_voice() -> String {
+    "Salli".to_string()
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// `path` without `.`, `..` and symbolic links. Files not created yet are
+/// resolved through their directory, if it exists, and kept as they are
+/// otherwise.
+fn canonical(path: &path::Path) -> path::PathBuf {
+    fs::canonicalize(path)
+        .ok()
+        .or_else(|| {
+            let dir = fs::canonicalize(path.parent()?).ok()?;
+            Some(dir.join(path.file_name()?))
+        })
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A configuration in a directory of its own, with jump bridges in
+    /// `bridges`, relative to it.
+    fn config(name: &str, bridges: &str) -> (Config, path::PathBuf) {
+        let dir = env::temp_dir().join(format!("eintel-config-{}", name));
+        fs::create_dir_all(dir.join("data")).unwrap();
+        let source = dir.join("config.toml");
+        fs::write(
+            &source,
+            format!("[routing]\njump_bridges = \"{}\"\n", bridges),
+        )
+        .unwrap();
+        (
+            Config::from_file(&source).unwrap(),
+            fs::canonicalize(&dir).unwrap(),
+        )
+    }
+
+    #[test]
+    fn depends_on_files_named_with_dots() {
+        let (conf, dir) = config("dots", "./data/../data/bridges.txt");
+        fs::write(dir.join("data/bridges.txt"), "").unwrap();
+
+        assert!(conf.depends_on(&dir.join("data/bridges.txt")));
+        assert!(conf.depends_on(&dir.join("data/./bridges.txt")));
+        assert!(!conf.depends_on(&dir.join("data/wormholes.txt")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn depends_on_files_through_symbolic_links() {
+        let (conf, dir) = config("links", "linked/bridges.txt");
+        fs::write(dir.join("data/bridges.txt"), "").unwrap();
+        fs::remove_file(dir.join("linked")).ok();
+        ::std::os::unix::fs::symlink(dir.join("data"), dir.join("linked")).unwrap();
+
+        assert!(conf.depends_on(&dir.join("data/bridges.txt")));
+    }
+
+    #[test]
+    fn depends_on_files_not_created_yet() {
+        let (conf, dir) = config("missing", "./data/later.txt");
+        fs::remove_file(dir.join("data/later.txt")).ok();
+
+        assert!(conf.depends_on(&dir.join("data/later.txt")));
+    }
 }
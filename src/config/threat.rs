@@ -0,0 +1,208 @@
+use super::super::errors::*;
+
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThreatLevel {
+    Critical,
+    High,
+    Low,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThreatBand {
+    pub name: String,
+    pub level: ThreatLevel,
+    #[serde(default)]
+    pub min: u16,
+    pub max: u16,
+}
+
+impl ThreatBand {
+    fn new(name: &str, level: ThreatLevel, min: u16, max: u16) -> ThreatBand {
+        ThreatBand {
+            name: name.to_string(),
+            level: level,
+            min: min,
+            max: max,
+        }
+    }
+
+    pub fn contains(&self, distance: u16) -> bool {
+        self.min <= distance && distance <= self.max
+    }
+}
+
+/// Bands a distance is checked against, in order, the distance up to which
+/// a "clear" report is still announced and the number of hostiles from which
+/// a report is taken one level more seriously.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThreatBands {
+    pub clear: u16,
+    pub bands: Vec<ThreatBand>,
+    pub fleet: u32,
+}
+
+impl ThreatBands {
+    pub fn matching(&self, distance: u16) -> Option<&ThreatBand> {
+        self.bands.iter().find(|band| band.contains(distance))
+    }
+
+    /// Farthest distance any band covers.
+    pub fn reach(&self) -> u16 {
+        self.bands.iter().map(|band| band.max).max().unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThreatProfile {
+    pub clear: Option<u16>,
+    pub bands: Option<Vec<ThreatBand>>,
+    pub fleet: Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThreatSettings {
+    #[serde(default = "default_clear")]
+    pub clear: u16,
+    #[serde(default = "default_bands")]
+    pub bands: Vec<ThreatBand>,
+    #[serde(default = "default_fleet")]
+    pub fleet: u32,
+    /// Minutes after which what was last reported about a system is
+    /// forgotten.
+    #[serde(default = "default_decay")]
+    pub decay: u32,
+    /// Ship classes making a report one level more serious.
+    #[serde(default = "default_dangerous_ships")]
+    pub dangerous_ships: Vec<String>,
+    /// Ship classes making a report one level less serious when every ship
+    /// reported is one of them.
+    #[serde(default = "default_harmless_ships")]
+    pub harmless_ships: Vec<String>,
+    #[serde(default)]
+    pub players: HashMap<String, ThreatProfile>,
+}
+
+impl Default for ThreatSettings {
+    fn default() -> ThreatSettings {
+        ThreatSettings {
+            clear: default_clear(),
+            bands: default_bands(),
+            fleet: default_fleet(),
+            decay: default_decay(),
+            dangerous_ships: default_dangerous_ships(),
+            harmless_ships: default_harmless_ships(),
+            players: HashMap::new(),
+        }
+    }
+}
+
+impl ThreatSettings {
+    /// The global bands with the overrides configured for `player` applied.
+    pub fn for_player(&self, player: &str) -> ThreatBands {
+        let profile = self.players.get(player);
+
+        ThreatBands {
+            clear: profile
+                .and_then(|profile| profile.clear)
+                .unwrap_or(self.clear),
+            bands: profile
+                .and_then(|profile| profile.bands.clone())
+                .unwrap_or_else(|| self.bands.clone()),
+            fleet: profile
+                .and_then(|profile| profile.fleet)
+                .unwrap_or(self.fleet),
+        }
+    }
+
+    pub fn is_dangerous(&self, class: &str) -> bool {
+        contains(&self.dangerous_ships, class)
+    }
+
+    pub fn is_harmless(&self, class: &str) -> bool {
+        contains(&self.harmless_ships, class)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.decay == 0 {
+            bail!("threat decay must be at least one minute");
+        }
+
+        let profiles = self
+            .players
+            .values()
+            .filter_map(|profile| profile.bands.as_ref());
+
+        for band in self
+            .bands
+            .iter()
+            .chain(profiles.flat_map(|bands| bands.iter()))
+        {
+            if band.min > band.max {
+                bail!(
+                    "threat band '{}' starts at {} jumps but ends at {}",
+                    band.name,
+                    band.min,
+                    band.max
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn contains(classes: &[String], class: &str) -> bool {
+    classes
+        .iter()
+        .any(|listed| listed.to_uppercase() == class.to_uppercase())
+}
+
+fn default_clear() -> u16 {
+    5
+}
+
+fn default_fleet() -> u32 {
+    10
+}
+
+fn default_decay() -> u32 {
+    10
+}
+
+fn default_dangerous_ships() -> Vec<String> {
+    [
+        "Interdictor",
+        "Heavy Interdiction Cruiser",
+        "Force Recon Ship",
+        "Black Ops",
+        "Cyno",
+        "Dreadnought",
+        "Carrier",
+        "Force Auxiliary",
+        "Supercarrier",
+        "Titan",
+    ]
+    .iter()
+    .map(|class| class.to_string())
+    .collect()
+}
+
+fn default_harmless_ships() -> Vec<String> {
+    ["Shuttle", "Capsule", "Corvette", "Mining Barge", "Exhumer"]
+        .iter()
+        .map(|class| class.to_string())
+        .collect()
+}
+
+fn default_bands() -> Vec<ThreatBand> {
+    vec![
+        ThreatBand::new("critical", ThreatLevel::Critical, 0, 0),
+        ThreatBand::new("high", ThreatLevel::High, 1, 4),
+        ThreatBand::new("low", ThreatLevel::Low, 5, 10),
+    ]
+}
@@ -3,7 +3,7 @@ use std;
 // use bincode;
 use log;
 // use reqwest;
-// use serde_json;
+use serde_json;
 // use time;
 use super::chat::channel;
 use super::events::Event;
@@ -12,6 +12,7 @@ use chrono;
 use notify;
 use std::sync;
 use std::sync::mpsc;
+use toml;
 
 error_chain!{
     foreign_links {
@@ -23,5 +24,7 @@ error_chain!{
         FSError(notify::Error);
         ChannelUnwrapError(sync::PoisonError<channel::Channel>);
         NotificationDebounceError(mpsc::SendError<debounced_message::DebounceMessages>);
+        ConfigParseError(toml::de::Error);
+        JsonError(serde_json::Error);
     }
 }
@@ -1,4 +1,5 @@
 use super::chat;
+use super::config;
 use super::intel;
 
 #[derive(Debug)]
@@ -6,4 +7,5 @@ pub enum Event {
     PreviousMessage(chat::Message),
     NewMessage(chat::Message),
     IntelReport(intel::Message),
+    ConfigChanged(config::Config),
 }
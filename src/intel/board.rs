@@ -0,0 +1,285 @@
+use super::message::Message;
+use super::universe;
+
+use chrono::prelude::*;
+use chrono::Duration;
+use std::collections::HashMap;
+
+/// What a report says about a system.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Hostiles are there.
+    Hostile,
+    /// Hostiles are gone, or there were none.
+    Clear,
+    /// Someone asked whether hostiles are there and nobody answered yet.
+    Requested,
+}
+
+/// What is known about a system from the reports about it.
+#[derive(Clone, Debug)]
+pub struct SystemState {
+    pub system: universe::System,
+    pub status: Status,
+    /// Number of hostiles last reported, if a report said.
+    pub hostiles: Option<u32>,
+    /// Ships and pilots reported since the system turned hostile.
+    pub ships: Vec<universe::ShipType>,
+    pub players: Vec<String>,
+    /// Who reported last, in which channel and when.
+    pub reporter: String,
+    pub channel: String,
+    pub reported_at: DateTime<Utc>,
+}
+
+impl SystemState {
+    /// How much of what is known still holds at `now`, from 1 when just
+    /// reported down to 0 once `decay` has passed.
+    pub fn freshness(&self, now: &DateTime<Utc>, decay: Duration) -> f64 {
+        let age = now.signed_duration_since(self.reported_at);
+        let decay = decay.num_seconds().max(1) as f64;
+        (1.0 - age.num_seconds().max(0) as f64 / decay).max(0.0)
+    }
+}
+
+/// What is known about a system near one somebody asked the status of.
+#[derive(Clone, Debug)]
+pub struct Known {
+    pub state: SystemState,
+    /// Jumps from the system asked about.
+    pub jumps: u16,
+    /// Time since the last report about the system.
+    pub age: Duration,
+}
+
+/// The state of every system reported on, each forgotten `decay` after the
+/// last report about it. A clear report cancels hostiles reported before, a
+/// status request only counts when nothing is known.
+pub struct Board {
+    systems: HashMap<String, SystemState>,
+    decay: Duration,
+}
+
+impl Board {
+    pub fn new(decay: Duration) -> Board {
+        Board {
+            systems: HashMap::new(),
+            decay: decay,
+        }
+    }
+
+    pub fn set_decay(&mut self, decay: Duration) {
+        self.decay = decay;
+    }
+
+    /// The state of `system` at `now`, unless it decayed.
+    pub fn get(&self, system: &universe::System, now: &DateTime<Utc>) -> Option<&SystemState> {
+        self.systems
+            .get(&system.id)
+            .filter(|state| state.freshness(now, self.decay) > 0.0)
+    }
+
+    /// What is known at `now` about the systems up to `jumps` away from
+    /// `system`, nearest and freshest first. Requests nobody answered yet
+    /// tell nothing and are left out.
+    pub fn around(
+        &self,
+        universe: &universe::Universe,
+        options: &universe::RouteOptions,
+        system: &universe::System,
+        jumps: u16,
+        now: &DateTime<Utc>,
+    ) -> Vec<Known> {
+        let mut known = self
+            .systems
+            .values()
+            .filter(|state| state.status != Status::Requested)
+            .filter(|state| state.freshness(now, self.decay) > 0.0)
+            .filter_map(|state| {
+                let distance = universe::distance(universe, system, &state.system, options)?;
+                if distance > jumps {
+                    return None;
+                }
+                Some(Known {
+                    state: state.clone(),
+                    jumps: distance,
+                    age: now.signed_duration_since(state.reported_at),
+                })
+            })
+            .collect::<Vec<_>>();
+        known.sort_by(|a, b| a.jumps.cmp(&b.jumps).then_with(|| a.age.cmp(&b.age)));
+        known
+    }
+
+    /// Records on each sighting of `message` what was known before it, and
+    /// on `message` what was known about the system taking priority.
+    pub fn recall(&self, message: &mut Message) {
+        let now = message.received_at;
+        for sighting in message.sightings.iter_mut() {
+            sighting.previous = self.get(&sighting.system, &now).map(|state| state.status);
+        }
+        message.previous = message
+            .sightings
+            .first()
+            .and_then(|priority| priority.previous);
+    }
+
+    /// Applies `message` to the systems it mentions.
+    pub fn update(&mut self, message: &Message) {
+        let now = message.received_at;
+        let status = message.status;
+        self.forget(&now);
+
+        for sighting in message.sightings.iter() {
+            if status == Status::Requested && self.get(&sighting.system, &now).is_some() {
+                continue;
+            }
+
+            let state = self
+                .systems
+                .entry(sighting.system.id.clone())
+                .or_insert_with(|| SystemState {
+                    system: sighting.system.clone(),
+                    status: status,
+                    hostiles: None,
+                    ships: vec![],
+                    players: vec![],
+                    reporter: String::new(),
+                    channel: String::new(),
+                    reported_at: now,
+                });
+            if state.status != Status::Hostile || message.status != Status::Hostile {
+                state.hostiles = None;
+                state.ships.clear();
+                state.players.clear();
+            }
+            if message.status == Status::Hostile {
+                if message.hostiles.is_some() {
+                    state.hostiles = message.hostiles;
+                }
+                for ship in message.ships.iter() {
+                    if !state.ships.iter().any(|known| known.name == ship.name) {
+                        state.ships.push(ship.clone());
+                    }
+                }
+                for player in message.involved_players.iter() {
+                    if !state.players.contains(&player.name) {
+                        state.players.push(player.name.clone());
+                    }
+                }
+            }
+            state.status = message.status;
+            state.reporter = message.sender.clone();
+            state.channel = message.channel.name.clone();
+            state.reported_at = now;
+        }
+    }
+
+    fn forget(&mut self, now: &DateTime<Utc>) {
+        let decay = self.decay;
+        self.systems
+            .retain(|_, state| state.freshness(now, decay) > 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intel::STATUS_JUMPS;
+    use universe::testing;
+
+    /// Home, then a line of systems One to Three away from it.
+    fn universe() -> universe::Universe {
+        testing::universe(
+            &["Home", "One", "Two", "Three"],
+            &[("Home", "One"), ("One", "Two"), ("Two", "Three")],
+            &[],
+        )
+    }
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        Utc.ymd(2018, 1, 1).and_hms(12, 0, 0) + Duration::minutes(minutes)
+    }
+
+    fn report(
+        universe: &universe::Universe,
+        system: &str,
+        status: Status,
+        minutes: i64,
+    ) -> Message {
+        let mut message = Message::report(universe.find(system).unwrap(), at(minutes));
+        message.status = status;
+        message
+    }
+
+    #[test]
+    fn forgets_systems_after_decay() {
+        let universe = universe();
+        let home = universe.find("Home").unwrap();
+        let mut board = Board::new(Duration::minutes(10));
+
+        board.update(&report(&universe, "Home", Status::Hostile, 0));
+
+        assert!(board.get(home, &at(9)).is_some());
+        assert!(board.get(home, &at(10)).is_none());
+    }
+
+    #[test]
+    fn clear_then_hostile_reports_hostiles_again() {
+        let universe = universe();
+        let home = universe.find("Home").unwrap();
+        let mut board = Board::new(Duration::minutes(10));
+        let mut hostile = report(&universe, "Home", Status::Hostile, 2);
+        hostile.hostiles = Some(3);
+
+        board.update(&report(&universe, "Home", Status::Clear, 0));
+        board.recall(&mut hostile);
+        board.update(&hostile);
+
+        assert_eq!(hostile.previous, Some(Status::Clear));
+        let state = board.get(home, &at(2)).unwrap();
+        assert_eq!(state.status, Status::Hostile);
+        assert_eq!(state.hostiles, Some(3));
+    }
+
+    #[test]
+    fn hostiles_cleared_are_forgotten() {
+        let universe = universe();
+        let home = universe.find("Home").unwrap();
+        let mut board = Board::new(Duration::minutes(10));
+        let mut hostile = report(&universe, "Home", Status::Hostile, 0);
+        hostile.hostiles = Some(3);
+
+        board.update(&hostile);
+        board.update(&report(&universe, "Home", Status::Clear, 1));
+
+        let state = board.get(home, &at(1)).unwrap();
+        assert_eq!(state.status, Status::Clear);
+        assert_eq!(state.hostiles, None);
+    }
+
+    #[test]
+    fn status_covers_systems_within_status_jumps() {
+        let universe = universe();
+        let home = universe.find("Home").unwrap();
+        let mut board = Board::new(Duration::minutes(10));
+
+        board.update(&report(&universe, "Three", Status::Hostile, 0));
+        board.update(&report(&universe, "Two", Status::Hostile, 1));
+        board.update(&report(&universe, "Home", Status::Clear, 2));
+        board.update(&report(&universe, "One", Status::Requested, 3));
+
+        let known = board.around(
+            &universe,
+            &universe::RouteOptions::default(),
+            home,
+            STATUS_JUMPS,
+            &at(3),
+        );
+        let names = known
+            .iter()
+            .map(|known| (known.state.system.name.as_str(), known.jumps))
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![("Home", 0), ("Two", 2)]);
+    }
+}
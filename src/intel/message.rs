@@ -1,103 +1,625 @@
+use super::board::{Known, Status};
 use super::chat;
+use super::config;
+use super::players;
+use super::tracker::Approach;
 use super::universe;
-use std::collections::HashSet;
+
+use chrono::prelude::*;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 pub enum ThreatAssetment {
-    Unknown,
     NoThreat(universe::System),
     ProximityIrelevant(u16),
-    ProximityAlertLow(u16),
-    ProximityAlertHigh(u16),
-    ProximityAlertCritical(u16),
+    ProximityAlertLow(u16, config::ThreatBand),
+    ProximityAlertHigh(u16, config::ThreatBand),
+    ProximityAlertCritical(u16, config::ThreatBand),
     StatusRequest(universe::System),
 }
 
+impl ThreatAssetment {
+    /// The band an alert is announced with.
+    pub fn band(&self) -> Option<&config::ThreatBand> {
+        match *self {
+            ThreatAssetment::ProximityAlertLow(_, ref band)
+            | ThreatAssetment::ProximityAlertHigh(_, ref band)
+            | ThreatAssetment::ProximityAlertCritical(_, ref band) => Some(band),
+            _ => None,
+        }
+    }
+}
+
+/// A system mentioned in an intel line, the route to it from the listener
+/// and what the line means for the listener there.
+#[derive(Debug, Clone)]
+pub struct Sighting {
+    pub system: universe::System,
+    /// Where in the system, if the line says.
+    pub place: Option<Place>,
+    /// Whether the hostiles sit on the gate the route from the listener
+    /// comes in through.
+    pub on_route: bool,
+    /// Jumps from the listener.
+    pub jumps: u16,
+    pub threat_assement: ThreatAssetment,
+    /// What was known about the system before the line, if anything.
+    pub previous: Option<Status>,
+}
+
+/// Where in a system hostiles are, from lines like `2 on 1DQ gate in ABC`
+/// or `on Y citadel`.
+#[derive(Debug, Clone)]
+pub enum Place {
+    /// On the stargate leading to the system.
+    Gate(universe::System),
+    /// At a station or structure of the kind named, e.g. `citadel`.
+    Structure(String),
+}
+
+/// Where reported hostiles are heading, from lines like `X > Y` or `gang in
+/// X moving to Y`.
+#[derive(Debug, Clone)]
+pub struct Movement {
+    pub from: Option<universe::System>,
+    pub to: universe::System,
+}
+
+/// Most words in a ship name, as in `Imperial Navy Slicer` or `Stratios
+/// Emergency Responder`.
+const MAX_SHIP_NAME_WORDS: usize = 4;
+
+/// Words a number of hostiles is followed by, as in `3 reds`.
+const HOSTILE_NOUNS: [&'static str; 12] = [
+    "RED", "REDS", "NEUT", "NEUTS", "NEUTRAL", "NEUTRALS", "HOSTILE", "HOSTILES", "GUYS", "PILOTS",
+    "SHIPS", "CHARS",
+];
+
+/// Words reporting a system clear, or asking whether it is.
+const CLEAR_WORDS: [&'static str; 3] = ["CLR", "CLEAR", "CLEA"];
+const STATUS_WORDS: [&'static str; 3] = ["STS", "STATUS", "STAT"];
+
+const TRAILING_PUNCTUATION: [char; 5] = [',', '.', ';', ':', '!'];
+
+/// Words placing hostiles on the stargate to the system named next to them.
+const GATE_WORDS: [&'static str; 4] = ["GATE", "GATES", "STARGATE", "GATECAMP"];
+
+/// Words placing hostiles at a station or structure of the system named
+/// before them.
+const STRUCTURE_WORDS: [&'static str; 16] = [
+    "STATION",
+    "CITADEL",
+    "STRUCTURE",
+    "ASTRAHUS",
+    "FORTIZAR",
+    "KEEPSTAR",
+    "RAITARU",
+    "AZBEL",
+    "SOTIYO",
+    "ATHANOR",
+    "TATARA",
+    "POS",
+    "IHUB",
+    "TCU",
+    "UNDOCK",
+    "POCO",
+];
+
+/// Other words common in intel lines, next to those above never taken for a
+/// mistyped system name.
+const INTEL_WORDS: [&'static str; 21] = [
+    "GANG", "GANGS", "FLEET", "FLEETS", "CAMP", "CAMPS", "CAMPED", "CAMPING", "BUBBLE", "BUBBLES",
+    "BUBBLED", "CYNO", "CYNOS", "CLOAKY", "CLOAKED", "DOCK", "DOCKED", "LOCAL", "SPIKE", "NOTHING",
+    "INBOUND",
+];
+
+/// Words announcing that the next system mentioned is where hostiles go.
+const MOVEMENT_MARKERS: [&'static str; 12] = [
+    ">", "->", ">>", "=>", "MOVING", "HEADING", "HEADED", "GOING", "JUMPED", "JUMPING", "TOWARDS",
+    "TOWARD",
+];
+
 #[derive(Debug, Clone)]
 pub struct Message {
     pub message: String,
+    pub received_at: DateTime<Utc>,
     pub player: String,
     pub tokens: Vec<String>,
-    pub route: universe::Route,
+    /// Jumps to, system and assessment of the sighting taking priority,
+    /// the most threatening and nearest one.
+    pub jumps: u16,
     pub origin: universe::System,
-    pub involved_players: Vec<String>,
+    pub place: Option<Place>,
+    pub on_route: bool,
+    /// Pilots the line names, most certain first.
+    pub involved_players: Vec<players::Player>,
     pub threat_assement: ThreatAssetment,
+    /// Whether the line reports hostiles, a clear system or asks for the
+    /// status of one.
+    pub status: Status,
+    /// What was known before the line about the system taking priority.
+    pub previous: Option<Status>,
+    /// Number of hostiles reported, if the line says.
+    pub hostiles: Option<u32>,
+    /// Ships reported, by name or shorthand.
+    pub ships: Vec<universe::ShipType>,
+    /// Every system mentioned, the one taking priority first.
+    pub sightings: Vec<Sighting>,
+    pub movement: Option<Movement>,
+    /// The last move of the gang reported, when it brought it closer to the
+    /// listener.
+    pub approach: Option<Approach>,
+    /// For status requests, what is known about the system and those
+    /// around it.
+    pub known: Vec<Known>,
     pub sender: String,
+    pub channel: config::Channel,
 }
 
 impl Message {
-    pub fn new(message: chat::Message, location: &universe::System) -> Option<Message> {
-        let line = normalize(&message.message);
-        let tokens = tokenize(line.clone());
-        let (route, tokens) = Self::route(&tokens, &location);
-        let system = route.as_ref().map(|r| r.destination.clone());
-        let (threat_level, tokens) = assess_thread_level(tokens, &route);
-        let players = possible_names(line.clone());
+    pub fn new(
+        message: chat::Message,
+        location: &universe::System,
+        conf: &config::Config,
+        channel: &config::Channel,
+        universe: &universe::Universe,
+        roster: &players::Roster,
+    ) -> Option<Message> {
+        let threat = conf.threat.for_player(&message.listener);
+        let links = players::links(&message.message);
+        let line = normalize(universe, &links.content);
+        let tokens = tokenize(universe, line);
+        let (systems, tokens) = Self::locate(
+            universe,
+            &tokens,
+            &location,
+            &conf.routing.intel,
+            threat.reach(),
+        );
+
+        let (places, gates) = places(&words(&links.content), &systems);
+        let mut mentioned = systems.values().cloned().collect::<Vec<_>>();
+        mentioned.sort_by(|a, b| a.id.cmp(&b.id));
+        mentioned.dedup_by(|a, b| a.id == b.id);
+        mentioned.retain(|system| !gates.contains(&system.id));
+
+        let hostiles = hostiles(&words(&links.content));
+        let ships = ships(universe, &words(&links.content));
+        let tokens = tokens
+            .into_iter()
+            .filter(|token| count(token).is_none())
+            .collect::<Vec<_>>();
+
+        let status = status(&tokens);
+        let mut remaining = tokens.clone();
+        let mut sightings = mentioned
+            .into_iter()
+            .filter_map(|system| {
+                let options = &conf.routing.intel;
+                let jumps = universe::distance(universe, location, system, options)?;
+                let (threat_level, rest) =
+                    assess_thread_level(tokens.clone(), jumps, system, &threat);
+                remaining = rest;
+                let place = places.get(&system.id).cloned();
+                let on_route = match place {
+                    Some(Place::Gate(ref gate)) => {
+                        arrives_through(universe, options, location, gate, jumps)
+                    }
+                    _ => false,
+                };
+                Some(Sighting {
+                    system: system.clone(),
+                    place: place,
+                    on_route: on_route,
+                    jumps: jumps,
+                    threat_assement: assess_ships(
+                        escalate(
+                            weigh(threat_level, channel.trust, &threat),
+                            hostiles,
+                            &threat,
+                        ),
+                        &ships,
+                        &conf.threat,
+                        &threat,
+                    ),
+                    previous: None,
+                })
+            })
+            .collect::<Vec<_>>();
+        sightings.sort_by(|a, b| {
+            severity(&b.threat_assement)
+                .cmp(&severity(&a.threat_assement))
+                .then_with(|| a.jumps.cmp(&b.jumps))
+                .then_with(|| a.system.name.cmp(&b.system.name))
+        });
+
+        let movement = movement(&links.content, &systems);
+        let players = players::involved(universe, &links, roster);
+        let priority = sightings.first()?.clone();
 
         Some({
             Message {
                 player: message.listener.clone(),
-                message: message.message.clone(),
-                tokens: tokens,
-                route: route?,
-                origin: system?,
+                message: links.text.clone(),
+                received_at: message.received_at,
+                tokens: remaining,
+                jumps: priority.jumps,
+                origin: priority.system,
+                place: priority.place,
+                on_route: priority.on_route,
                 involved_players: players,
-                threat_assement: threat_level,
+                threat_assement: priority.threat_assement,
+                status: status,
+                previous: None,
+                hostiles: hostiles,
+                ships: ships,
+                sightings: sightings,
+                movement: movement,
+                approach: None,
+                known: vec![],
                 sender: message.sender.clone(),
+                channel: channel.clone(),
             }
         })
     }
 
-    fn route(
+    /// Takes the report one level more seriously, the gang it is about
+    /// closing in on the listener, whose bands are `threat`.
+    pub fn approaching(&mut self, approach: Approach, threat: &config::ThreatBands) {
+        self.threat_assement = raise(self.threat_assement.clone(), threat);
+        if let Some(priority) = self.sightings.first_mut() {
+            priority.threat_assement = self.threat_assement.clone();
+        }
+        self.approach = Some(approach);
+    }
+
+    /// Resolves the tokens naming systems near `destination`, by token.
+    /// Tokens only resembling a system name count when no token names one
+    /// outright.
+    fn locate<'a>(
+        universe: &'a universe::Universe,
         tokens: &Vec<String>,
         destination: &universe::System,
-    ) -> (Option<universe::Route>, Vec<String>) {
-        let mut system_names = HashSet::new();
-        let mut routes = tokens
-            .iter()
-            .map(|ref mut token| {
-                let x = token.clone();
-                universe::System::find(&token).and_then(|system| {
-                    system_names.insert(x);
-                    Some(system)
-                })
-            })
-            .filter_map(|system| universe::route(destination, &system?))
-            .collect::<Vec<_>>();
+        options: &universe::RouteOptions,
+        reach: u16,
+    ) -> (HashMap<String, &'a universe::System>, Vec<String>) {
+        let vicinity = universe::Vicinity {
+            origin: destination,
+            options: options,
+            reach: reach,
+        };
+        let mut certain = HashMap::new();
+        let mut resembling = HashMap::new();
 
-        routes.sort_by(|a, b| a.distance.cmp(&b.distance));
+        for token in tokens.iter() {
+            // `x5` or `2x` are numbers of hostiles, even where they are the
+            // short form of a system too
+            if count(token).is_some() || token.parse::<u32>().is_ok() {
+                continue;
+            }
+            let mut candidates = universe.resolve(token, Some(&vicinity));
+            if is_intel_word(token) {
+                candidates.retain(|candidate| candidate.kind.is_certain());
+            }
+            let best = match candidates.first() {
+                Some(best) => best.clone(),
+                None => continue,
+            };
+            if candidates.len() > 1 {
+                debug!(
+                    "{} is ambiguous, picked {} out of {}",
+                    token,
+                    best.system.name,
+                    describe(&candidates)
+                );
+            }
+            if best.distance.is_none() {
+                continue;
+            }
+            if best.kind.is_certain() {
+                certain.insert(token.clone(), best.system);
+            } else {
+                resembling.insert(token.clone(), best.system);
+            }
+        }
 
+        let systems = if certain.is_empty() {
+            resembling
+        } else {
+            certain
+        };
         let new_tokens = tokens
             .iter()
-            .filter(|token| !system_names.contains(token.clone()))
+            .filter(|token| !systems.contains_key(token.as_str()))
             .map(|token| token.to_owned())
             .collect::<Vec<_>>();
+        (systems, new_tokens)
+    }
+}
 
-        (routes.pop(), new_tokens)
+/// Whether `token` is one of the words intel lines are made of, or a number,
+/// rather than a system name.
+fn is_intel_word(token: &str) -> bool {
+    HOSTILE_NOUNS.contains(&token)
+        || CLEAR_WORDS.contains(&token)
+        || STATUS_WORDS.contains(&token)
+        || GATE_WORDS.contains(&token)
+        || STRUCTURE_WORDS.contains(&token)
+        || MOVEMENT_MARKERS.contains(&token)
+        || INTEL_WORDS.contains(&token)
+        || count(token).is_some()
+        || token.parse::<u32>().is_ok()
+}
+
+/// The largest number of hostiles a line reports, written as `+5`, `5+`,
+/// `x15`, `15x`, `3 reds` or `10 in local`.
+fn hostiles(words: &[String]) -> Option<u32> {
+    let mut counts = vec![];
+    for (index, word) in words.iter().enumerate() {
+        if let Some(count) = count(word) {
+            counts.push(count);
+            continue;
+        }
+        let number = match word.parse::<u32>() {
+            Ok(number) => number,
+            Err(_) => continue,
+        };
+        let next = words.get(index + 1).map(|word| word.as_str());
+        let after = words.get(index + 2).map(|word| word.as_str());
+        let counted = next
+            .map(|next| HOSTILE_NOUNS.contains(&next))
+            .unwrap_or(false)
+            || (next == Some("IN") && after == Some("LOCAL"));
+        if counted {
+            counts.push(number);
+        }
+    }
+    counts.into_iter().max()
+}
+
+/// The number of a word standing for a count on its own, as `+5` or `x15`.
+fn count(word: &str) -> Option<u32> {
+    let number = if word.starts_with('+') || word.starts_with('X') {
+        &word[1..]
+    } else if word.ends_with('+') || word.ends_with('X') {
+        &word[..word.len() - 1]
+    } else {
+        return None;
+    };
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
     }
+    number.parse().ok()
 }
 
-fn tokenize(text: String) -> Vec<String> {
-    text.split_whitespace()
+/// Ships named in `words`, longest names first so `Caracal Navy Issue` is
+/// not taken for a `Caracal`. Plurals like `dics` are understood.
+fn ships(universe: &universe::Universe, words: &[String]) -> Vec<universe::ShipType> {
+    let mut ships = vec![];
+    let mut index = 0;
+
+    while index < words.len() {
+        let longest = (1..MAX_SHIP_NAME_WORDS + 1)
+            .rev()
+            .filter(|length| index + length <= words.len())
+            .filter_map(|length| {
+                let name = words[index..index + length].join(" ");
+                let ship = universe
+                    .ship(&name)
+                    .or_else(|| universe.ship(singular(&name)?))?;
+                Some((length, ship))
+            })
+            .next();
+
+        match longest {
+            Some((length, ship)) => {
+                ships.push(ship);
+                index += length;
+            }
+            None => index += 1,
+        }
+    }
+    ships
+}
+
+/// `name` without the `S` of a plural, if it ends with one.
+fn singular(name: &str) -> Option<&str> {
+    if name.len() > 1 && name.ends_with('S') {
+        Some(&name[..name.len() - 1])
+    } else {
+        None
+    }
+}
+
+/// Places named in `words`, by the id of the system they are in, and the
+/// systems only named as where a gate leads, which the hostiles are not in.
+/// A gate is placed in the one other system mentioned next to the system
+/// it leads to, a structure in the system named before it or, failing
+/// that, the only system mentioned.
+fn places(
+    words: &[String],
+    systems: &HashMap<String, &universe::System>,
+) -> (HashMap<String, Place>, Vec<String>) {
+    let system_at = |index: usize| words.get(index).and_then(|word| systems.get(word)).cloned();
+    let mut gates = vec![];
+    let mut structures = vec![];
+
+    for (index, word) in words.iter().enumerate() {
+        let before = index.checked_sub(1).and_then(&system_at);
+        if GATE_WORDS.contains(&word.as_str()) {
+            let to = match words.get(index + 1).map(|word| word.as_str()) {
+                Some("TO") => system_at(index + 2),
+                _ => None,
+            };
+            if let Some(gate) = to.or(before).or_else(|| system_at(index + 1)) {
+                gates.push(gate);
+            }
+        } else if STRUCTURE_WORDS.contains(&word.as_str()) {
+            structures.push((before, word.to_lowercase()));
+        }
+    }
+
+    let mut mentioned = systems.values().cloned().collect::<Vec<_>>();
+    mentioned.sort_by(|a, b| a.id.cmp(&b.id));
+    mentioned.dedup_by(|a, b| a.id == b.id);
+
+    let mut places = HashMap::new();
+    let mut destinations = vec![];
+    for gate in gates {
+        let sides = mentioned
+            .iter()
+            .filter(|system| system.id != gate.id && system.neighbours.contains(&gate.id))
+            .collect::<Vec<_>>();
+        if sides.len() == 1 {
+            places.insert(sides[0].id.clone(), Place::Gate(gate.clone()));
+            destinations.push(gate.id.clone());
+        }
+    }
+
+    mentioned.retain(|system| !destinations.contains(&system.id));
+    for (system, kind) in structures {
+        let system = system
+            .filter(|system| !destinations.contains(&system.id))
+            .or_else(|| match mentioned.len() {
+                1 => Some(mentioned[0]),
+                _ => None,
+            });
+        if let Some(system) = system {
+            places
+                .entry(system.id.clone())
+                .or_insert(Place::Structure(kind));
+        }
+    }
+    (places, destinations)
+}
+
+/// Whether a route from `location` to the system `jumps` away that `gate`
+/// leads into can end with the jump through it.
+fn arrives_through(
+    universe: &universe::Universe,
+    options: &universe::RouteOptions,
+    location: &universe::System,
+    gate: &universe::System,
+    jumps: u16,
+) -> bool {
+    universe::distance(universe, location, gate, options)
+        .map(|to_gate| to_gate + 1 == jumps)
+        .unwrap_or(false)
+}
+
+/// Whether `tokens` report hostiles, a clear system or ask for the status
+/// of one.
+fn status(tokens: &[String]) -> Status {
+    if tokens
+        .iter()
+        .any(|token| CLEAR_WORDS.contains(&token.as_str()))
+    {
+        Status::Clear
+    } else if tokens
+        .iter()
+        .any(|token| STATUS_WORDS.contains(&token.as_str()))
+    {
+        Status::Requested
+    } else {
+        Status::Hostile
+    }
+}
+
+/// How much a sighting calls for attention, higher is more.
+fn severity(level: &ThreatAssetment) -> u8 {
+    match *level {
+        ThreatAssetment::ProximityAlertCritical(..) => 6,
+        ThreatAssetment::ProximityAlertHigh(..) => 5,
+        ThreatAssetment::ProximityAlertLow(..) => 4,
+        ThreatAssetment::StatusRequest(..) => 3,
+        ThreatAssetment::NoThreat(..) => 2,
+        ThreatAssetment::ProximityIrelevant(..) => 1,
+    }
+}
+
+/// The last move a line reports: a system following one of the
+/// `MOVEMENT_MARKERS`, coming from the system mentioned before it, if any.
+fn movement(text: &str, systems: &HashMap<String, &universe::System>) -> Option<Movement> {
+    let mut last: Option<&universe::System> = None;
+    let mut moving = false;
+    let mut movement = None;
+
+    for word in words(text).iter() {
+        if MOVEMENT_MARKERS.contains(&word.as_str()) {
+            moving = true;
+        } else if let Some(system) = systems.get(word) {
+            if moving && last.map(|last| last.id != system.id).unwrap_or(true) {
+                movement = Some(Movement {
+                    from: last.cloned(),
+                    to: (*system).clone(),
+                });
+            }
+            last = Some(system);
+            moving = false;
+        }
+    }
+    movement
+}
+
+/// Puts spaces around arrows, so `X>Y` reads as `X > Y`.
+fn separate_arrows(text: &str) -> String {
+    text.replace("->", " > ")
+        .replace("=>", " > ")
+        .replace(">", " > ")
+}
+
+fn describe(candidates: &[universe::Candidate]) -> String {
+    candidates
+        .iter()
+        .map(|candidate| {
+            format!(
+                "{} ({:?}, {:.0}%, {})",
+                candidate.system.name,
+                candidate.kind,
+                candidate.confidence * 100.0,
+                candidate
+                    .distance
+                    .map(|jumps| format!("{} jumps", jumps))
+                    .unwrap_or_else(|| "unreachable".to_string())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn tokenize(universe: &universe::Universe, text: String) -> Vec<String> {
+    words(&text)
+        .into_iter()
+        .filter(|word| !universe.is_stop_word(word))
+        .filter(|word| !universe.ship_exists(word))
+        .collect()
+}
+
+/// Upper cased words of `text`, arrows apart and without the punctuation
+/// ending sentences, as in `X, gang moving to Y.`
+fn words(text: &str) -> Vec<String> {
+    separate_arrows(text)
+        .split_whitespace()
         .map(|x| {
             x.to_uppercase()
                 .replace("*", "")
                 .replace("?", "")
                 .replace("Solar System -", "")
+                .trim_right_matches(|c| TRAILING_PUNCTUATION.contains(&c))
+                .to_string()
         })
-        .filter(stop_words)
-        .filter(ships)
+        .filter(|word| !word.is_empty())
         .collect()
 }
 
-fn normalize(text: &str) -> String {
+fn normalize(universe: &universe::Universe, text: &str) -> String {
     text.replace("*", "")
         .split("  ")
         .map(|x| {
             x.split(" ")
                 .map(|x| x.to_string())
-                .filter(ships)
-                .filter(stop_words)
+                .filter(|word| !universe.ship_exists(word))
+                .filter(|word| !universe.is_stop_word(word))
                 .collect::<Vec<String>>()
                 .join(" ")
         })
@@ -106,68 +628,142 @@ fn normalize(text: &str) -> String {
         .join("  ")
 }
 
-fn possible_names(line: String) -> Vec<String> {
-    line.split("  ")
-        .map(|token| token.to_string())
-        .filter(|token| !universe::System::find(token).is_some())
-        .filter(ships)
-        .filter(stop_words)
-        .map(|token| token.to_string())
-        .collect::<Vec<String>>()
+fn assess_thread_level(
+    tokens: Vec<String>,
+    jumps: u16,
+    system: &universe::System,
+    threat: &config::ThreatBands,
+) -> (ThreatAssetment, Vec<String>) {
+    let level = match threat.matching(jumps) {
+        Some(band) => alert(jumps, band.clone()),
+        None => ThreatAssetment::ProximityIrelevant(jumps),
+    };
+    let no_proximity = CLEAR_WORDS
+        .iter()
+        .map(|&x| x.to_owned())
+        .collect::<Vec<String>>();
+    let no_threat = STATUS_WORDS
+        .iter()
+        .map(|&x| x.to_owned())
+        .collect::<Vec<String>>();
+
+    if let Some(new_tokens) = tokens_difference(&tokens, no_proximity) {
+        if jumps <= threat.clear {
+            (ThreatAssetment::NoThreat(system.clone()), new_tokens)
+        } else {
+            (ThreatAssetment::ProximityIrelevant(jumps), new_tokens)
+        }
+    } else if let Some(new_tokens) = tokens_difference(&tokens, no_threat) {
+        (ThreatAssetment::StatusRequest(system.clone()), new_tokens)
+    } else {
+        (level, tokens)
+    }
 }
 
-fn stop_words(word: &String) -> bool {
-    !universe::is_stop_word(word)
+/// Reports from untrusted channels are taken one level less seriously, while
+/// trusted channels get their low level alerts escalated.
+fn weigh(
+    level: ThreatAssetment,
+    trust: config::Trust,
+    threat: &config::ThreatBands,
+) -> ThreatAssetment {
+    match (trust, level) {
+        (config::Trust::Low, level) => lower(level, threat),
+        (config::Trust::High, ThreatAssetment::ProximityAlertLow(jumps, _)) => {
+            relevel(jumps, config::ThreatLevel::High, threat)
+        }
+        (_, level) => level,
+    }
 }
 
-fn ships(token: &String) -> bool {
-    !universe::ship_exists(&token)
+/// Reports of at least `fleet` hostiles are taken one level more seriously.
+fn escalate(
+    level: ThreatAssetment,
+    hostiles: Option<u32>,
+    threat: &config::ThreatBands,
+) -> ThreatAssetment {
+    match hostiles {
+        Some(hostiles) if hostiles >= threat.fleet => raise(level, threat),
+        _ => level,
+    }
 }
 
-fn assess_thread_level(
-    tokens: Vec<String>,
-    route: &Option<universe::Route>,
-) -> (ThreatAssetment, Vec<String>) {
-    match route {
-        Some(route) => {
-            let mut tokens = tokens.clone();
-            let mut level = match route.distance {
-                1...4 => ThreatAssetment::ProximityAlertHigh(route.distance),
-                5...10 => ThreatAssetment::ProximityAlertLow(route.distance),
-                0 => ThreatAssetment::ProximityAlertCritical(route.distance),
-                _ => ThreatAssetment::ProximityIrelevant(route.distance),
-            };
-            let no_proximity = vec!["CLR", "CLEAR", "CLEA"]
-                .iter()
-                .map(|&x| x.to_owned())
-                .collect::<Vec<String>>();
-            let no_threat = vec!["STS", "STATUS", "STAT"]
-                .iter()
-                .map(|&x| x.to_owned())
-                .collect::<Vec<String>>();
-
-            if let Some(new_tokens) = tokens_difference(&tokens, no_proximity) {
-                if route.distance <= 5 {
-                    (
-                        ThreatAssetment::NoThreat(route.destination.clone()),
-                        new_tokens,
-                    )
-                } else {
-                    (
-                        ThreatAssetment::ProximityIrelevant(route.distance),
-                        new_tokens,
-                    )
-                }
-            } else if let Some(new_tokens) = tokens_difference(&tokens, no_threat) {
-                (
-                    ThreatAssetment::StatusRequest(route.destination.clone()),
-                    new_tokens,
-                )
+/// Reports of dangerous ships are taken one level more seriously, those of
+/// harmless ships only one level less.
+fn assess_ships(
+    level: ThreatAssetment,
+    ships: &[universe::ShipType],
+    settings: &config::ThreatSettings,
+    threat: &config::ThreatBands,
+) -> ThreatAssetment {
+    if ships.iter().any(|ship| settings.is_dangerous(&ship.class)) {
+        raise(level, threat)
+    } else if !ships.is_empty() && ships.iter().all(|ship| settings.is_harmless(&ship.class)) {
+        lower(level, threat)
+    } else {
+        level
+    }
+}
+
+fn raise(level: ThreatAssetment, threat: &config::ThreatBands) -> ThreatAssetment {
+    match level {
+        ThreatAssetment::ProximityAlertHigh(jumps, _) => {
+            relevel(jumps, config::ThreatLevel::Critical, threat)
+        }
+        ThreatAssetment::ProximityAlertLow(jumps, _) => {
+            relevel(jumps, config::ThreatLevel::High, threat)
+        }
+        level => level,
+    }
+}
+
+fn lower(level: ThreatAssetment, threat: &config::ThreatBands) -> ThreatAssetment {
+    match level {
+        ThreatAssetment::ProximityAlertCritical(jumps, _) => {
+            relevel(jumps, config::ThreatLevel::High, threat)
+        }
+        ThreatAssetment::ProximityAlertHigh(jumps, _) => {
+            relevel(jumps, config::ThreatLevel::Low, threat)
+        }
+        ThreatAssetment::ProximityAlertLow(jumps, _) => ThreatAssetment::ProximityIrelevant(jumps),
+        level => level,
+    }
+}
+
+/// The alert at `level` for hostiles `jumps` away, announced with the band
+/// of that level nearest to them, or with the level itself when no band has
+/// it.
+fn relevel(
+    jumps: u16,
+    level: config::ThreatLevel,
+    threat: &config::ThreatBands,
+) -> ThreatAssetment {
+    let band = threat
+        .bands
+        .iter()
+        .filter(|band| band.level == level)
+        .min_by_key(|band| {
+            if jumps < band.min {
+                band.min - jumps
             } else {
-                (level, tokens)
+                jumps.saturating_sub(band.max)
             }
-        }
-        None => (ThreatAssetment::Unknown, tokens),
+        })
+        .cloned()
+        .unwrap_or_else(|| config::ThreatBand {
+            name: format!("{:?}", level).to_lowercase(),
+            level: level,
+            min: jumps,
+            max: jumps,
+        });
+    alert(jumps, band)
+}
+
+fn alert(jumps: u16, band: config::ThreatBand) -> ThreatAssetment {
+    match band.level {
+        config::ThreatLevel::Critical => ThreatAssetment::ProximityAlertCritical(jumps, band),
+        config::ThreatLevel::High => ThreatAssetment::ProximityAlertHigh(jumps, band),
+        config::ThreatLevel::Low => ThreatAssetment::ProximityAlertLow(jumps, band),
     }
 }
 
@@ -189,3 +785,416 @@ fn tokens_difference(first: &Vec<String>, last: Vec<String>) -> Option<Vec<Strin
         None
     }
 }
+
+#[cfg(test)]
+impl Message {
+    /// A report of hostiles in `system` as of `received_at`, for tests to
+    /// fill in.
+    pub fn report(system: &universe::System, received_at: DateTime<Utc>) -> Message {
+        let threat_assement = ThreatAssetment::ProximityIrelevant(0);
+        Message {
+            message: String::new(),
+            received_at: received_at,
+            player: "Listener".to_string(),
+            tokens: vec![],
+            jumps: 0,
+            origin: system.clone(),
+            place: None,
+            on_route: false,
+            involved_players: vec![],
+            threat_assement: threat_assement.clone(),
+            status: Status::Hostile,
+            previous: None,
+            hostiles: None,
+            ships: vec![],
+            sightings: vec![Sighting {
+                system: system.clone(),
+                place: None,
+                on_route: false,
+                jumps: 0,
+                threat_assement: threat_assement,
+                previous: None,
+            }],
+            movement: None,
+            approach: None,
+            known: vec![],
+            sender: "Reporter".to_string(),
+            channel: config::Channel::new("Intel"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use universe::testing;
+
+    fn band(name: &str, level: config::ThreatLevel, min: u16, max: u16) -> config::ThreatBand {
+        config::ThreatBand {
+            name: name.to_string(),
+            level: level,
+            min: min,
+            max: max,
+        }
+    }
+
+    fn bands() -> config::ThreatBands {
+        config::ThreatBands {
+            clear: 3,
+            bands: vec![
+                band("home", config::ThreatLevel::Critical, 0, 1),
+                band("near", config::ThreatLevel::High, 2, 4),
+                band("region", config::ThreatLevel::Low, 5, 10),
+            ],
+            fleet: 10,
+        }
+    }
+
+    fn alert_at(jumps: u16) -> ThreatAssetment {
+        let threat = bands();
+        alert(jumps, threat.matching(jumps).unwrap().clone())
+    }
+
+    fn band_name(level: &ThreatAssetment) -> &str {
+        &level.band().unwrap().name
+    }
+
+    #[test]
+    fn low_trust_lowers_alerts_into_the_band_of_the_new_level() {
+        let level = weigh(alert_at(1), config::Trust::Low, &bands());
+
+        match level {
+            ThreatAssetment::ProximityAlertHigh(1, _) => {}
+            level => panic!("unexpected {:?}", level),
+        }
+        assert_eq!(band_name(&level), "near");
+    }
+
+    #[test]
+    fn low_trust_drops_low_alerts() {
+        match weigh(alert_at(7), config::Trust::Low, &bands()) {
+            ThreatAssetment::ProximityIrelevant(7) => {}
+            level => panic!("unexpected {:?}", level),
+        }
+    }
+
+    #[test]
+    fn high_trust_raises_low_alerts_into_the_band_of_the_new_level() {
+        let level = weigh(alert_at(7), config::Trust::High, &bands());
+
+        match level {
+            ThreatAssetment::ProximityAlertHigh(7, _) => {}
+            level => panic!("unexpected {:?}", level),
+        }
+        assert_eq!(band_name(&level), "near");
+    }
+
+    #[test]
+    fn normal_trust_keeps_alerts() {
+        let level = weigh(alert_at(3), config::Trust::Normal, &bands());
+
+        assert_eq!(band_name(&level), "near");
+    }
+
+    #[test]
+    fn levels_without_a_band_are_announced_by_name() {
+        let mut threat = bands();
+        threat
+            .bands
+            .retain(|band| band.level != config::ThreatLevel::High);
+        let level = weigh(alert_at(1), config::Trust::Low, &threat);
+
+        assert_eq!(band_name(&level), "high");
+    }
+
+    fn universe() -> universe::Universe {
+        testing::universe(
+            &[
+                "Jita",
+                "Perimeter",
+                "Rens",
+                "Gyng",
+                "Gare",
+                "Gateway",
+                "Fliet",
+                "X5O1-L",
+                "2X7Z-L",
+            ],
+            &[
+                ("Jita", "Perimeter"),
+                ("Perimeter", "Rens"),
+                ("Rens", "Gyng"),
+                ("Gyng", "Gare"),
+                ("Gare", "Gateway"),
+                ("Gateway", "Fliet"),
+                ("Jita", "X5O1-L"),
+                ("Perimeter", "2X7Z-L"),
+            ],
+            &["ON", "IN", "AT", "THE"],
+        )
+    }
+
+    /// Systems `line` mentions by token, heard in Jita.
+    fn mentioned<'a>(
+        universe: &'a universe::Universe,
+        line: &str,
+    ) -> HashMap<String, &'a universe::System> {
+        let origin = universe.find("Jita").unwrap();
+        let tokens = tokenize(universe, normalize(universe, line));
+        let options = universe::RouteOptions::default();
+        Message::locate(universe, &tokens, origin, &options, 10).0
+    }
+
+    /// Names of the systems `line` mentions, heard in Jita.
+    fn located(universe: &universe::Universe, line: &str) -> Vec<String> {
+        let mut names = mentioned(universe, line)
+            .values()
+            .map(|system| system.name.clone())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn intel_words_are_not_taken_for_mistyped_systems() {
+        let universe = universe();
+        for line in [
+            "3 reds on gate",
+            "gang on the gate",
+            "fleet inbound",
+            "+5 neuts at gate",
+            "15x hostiles",
+        ]
+        .iter()
+        {
+            assert!(
+                located(&universe, line).is_empty(),
+                "{} located {:?}",
+                line,
+                located(&universe, line)
+            );
+        }
+    }
+
+    #[test]
+    fn mistyped_systems_still_resolve() {
+        let universe = universe();
+
+        assert_eq!(located(&universe, "Jtia 3 reds"), vec!["Jita"]);
+        assert_eq!(located(&universe, "gang in Perimetr"), vec!["Perimeter"]);
+        assert_eq!(located(&universe, "Gateway gate"), vec!["Gateway"]);
+    }
+
+    #[test]
+    fn counts_are_not_taken_for_short_forms_of_systems() {
+        let universe = universe();
+        assert_eq!(universe.find("X5").unwrap().name, "X5O1-L");
+        assert_eq!(universe.find("2X").unwrap().name, "2X7Z-L");
+
+        assert_eq!(located(&universe, "Jita x5 sabre"), vec!["Jita"]);
+        assert_eq!(located(&universe, "Perimeter 2x loki"), vec!["Perimeter"]);
+        assert_eq!(located(&universe, "X5O1-L 5x"), vec!["X5O1-L"]);
+    }
+
+    /// Where `line` reports hostiles moving from and to, by name.
+    fn moved(universe: &universe::Universe, line: &str) -> Option<(Option<String>, String)> {
+        movement(line, &mentioned(universe, line))
+            .map(|movement| (movement.from.map(|from| from.name), movement.to.name))
+    }
+
+    #[test]
+    fn movement_follows_arrows() {
+        let universe = universe();
+        let expected = Some((Some("Rens".to_string()), "Gyng".to_string()));
+
+        assert_eq!(moved(&universe, "Rens > Gyng"), expected);
+        assert_eq!(moved(&universe, "Rens->Gyng"), expected);
+        assert_eq!(moved(&universe, "3 reds Rens => Gyng"), expected);
+    }
+
+    #[test]
+    fn movement_follows_words() {
+        let universe = universe();
+        let expected = Some((Some("Rens".to_string()), "Gyng".to_string()));
+
+        assert_eq!(moved(&universe, "gang in Rens jumped Gyng"), expected);
+        assert_eq!(moved(&universe, "Rens gang moving to Gyng"), expected);
+        assert_eq!(moved(&universe, "Rens jumped Gyng gate"), expected);
+    }
+
+    #[test]
+    fn movement_is_the_last_move_reported() {
+        let universe = universe();
+
+        assert_eq!(
+            moved(&universe, "Rens > Gyng > Gare"),
+            Some((Some("Gyng".to_string()), "Gare".to_string()))
+        );
+        assert_eq!(
+            moved(&universe, "gang jumped Gyng"),
+            Some((None, "Gyng".to_string()))
+        );
+    }
+
+    #[test]
+    fn lines_without_movement() {
+        let universe = universe();
+
+        for line in [
+            "3 reds in Rens",
+            "Rens Gyng",
+            "Rens > Rens",
+            "Rens gang jumped",
+            "Rens > ",
+        ]
+        .iter()
+        {
+            assert!(moved(&universe, line).is_none(), "{} moved", line);
+        }
+    }
+
+    fn hostiles_in(line: &str) -> Option<u32> {
+        hostiles(&words(line))
+    }
+
+    #[test]
+    fn counts_stand_for_numbers_of_hostiles() {
+        assert_eq!(count("+5"), Some(5));
+        assert_eq!(count("5+"), Some(5));
+        assert_eq!(count("X15"), Some(15));
+        assert_eq!(count("15X"), Some(15));
+
+        assert_eq!(count("5"), None);
+        assert_eq!(count("X"), None);
+        assert_eq!(count("+"), None);
+        assert_eq!(count("X5O1"), None);
+        assert_eq!(count("2X7Z-L"), None);
+    }
+
+    #[test]
+    fn hostiles_are_counted_by_counts_and_nouns() {
+        assert_eq!(hostiles_in("+5 in Rens"), Some(5));
+        assert_eq!(hostiles_in("Rens 15x"), Some(15));
+        assert_eq!(hostiles_in("x3 Rens gate"), Some(3));
+        assert_eq!(hostiles_in("3 reds Rens"), Some(3));
+        assert_eq!(hostiles_in("Rens 10 in local"), Some(10));
+        assert_eq!(hostiles_in("2 reds in Rens, +8 in Gyng"), Some(8));
+    }
+
+    #[test]
+    fn bare_numbers_are_not_hostiles() {
+        assert_eq!(hostiles_in("Rens 3"), None);
+        assert_eq!(hostiles_in("Rens 2 jumps out"), None);
+        assert_eq!(hostiles_in("Rens clear"), None);
+    }
+
+    #[test]
+    fn counts_looking_like_short_forms_of_systems_are_hostiles() {
+        let universe = universe();
+
+        assert_eq!(hostiles_in("Jita x5 sabre"), Some(5));
+        assert_eq!(located(&universe, "Jita x5 sabre"), vec!["Jita"]);
+        assert_eq!(hostiles_in("Perimeter 2x loki"), Some(2));
+        assert_eq!(located(&universe, "Perimeter 2x loki"), vec!["Perimeter"]);
+    }
+
+    fn ships_in(line: &str) -> Vec<String> {
+        let names = vec!["LOKI".to_string(), "SABRE".to_string(), "ARES".to_string()];
+        let universe = universe::Universe::new(vec![], names, vec![], vec![], None);
+        ships(&universe, &words(line))
+            .into_iter()
+            .map(|ship| ship.name)
+            .collect()
+    }
+
+    #[test]
+    fn ships_are_named_alone_or_in_plural() {
+        assert_eq!(ships_in("loki sabres"), vec!["Loki", "Sabre"]);
+        assert_eq!(ships_in("ares"), vec!["Ares"]);
+        assert_eq!(ships_in("sabress lokis"), vec!["Loki"]);
+    }
+
+    /// Places `line` reports, as the name of the system they are in and
+    /// what they are, and the systems only named as where a gate leads.
+    fn placed(universe: &universe::Universe, line: &str) -> (Vec<(String, String)>, Vec<String>) {
+        let (places, gates) = places(&words(line), &mentioned(universe, line));
+        let systems = universe.systems();
+        let name = |id: &String| {
+            systems
+                .iter()
+                .find(|system| &system.id == id)
+                .unwrap()
+                .name
+                .clone()
+        };
+        let mut places = places
+            .iter()
+            .map(|(id, place)| {
+                let place = match *place {
+                    Place::Gate(ref gate) => format!("{} gate", gate.name),
+                    Place::Structure(ref kind) => kind.clone(),
+                };
+                (name(id), place)
+            })
+            .collect::<Vec<_>>();
+        places.sort();
+        (places, gates.iter().map(name).collect())
+    }
+
+    fn place(system: &str, place: &str) -> (String, String) {
+        (system.to_string(), place.to_string())
+    }
+
+    #[test]
+    fn gates_are_placed_in_the_system_next_to_where_they_lead() {
+        let universe = universe();
+
+        assert_eq!(
+            placed(&universe, "2 on Rens gate in Gyng"),
+            (vec![place("Gyng", "Rens gate")], vec!["Rens".to_string()])
+        );
+        assert_eq!(
+            placed(&universe, "Gyng on gate to Rens"),
+            (vec![place("Gyng", "Rens gate")], vec!["Rens".to_string()])
+        );
+    }
+
+    #[test]
+    fn gates_between_several_systems_are_not_placed() {
+        let universe = universe();
+
+        assert_eq!(placed(&universe, "Rens Gare Gyng gate"), (vec![], vec![]));
+        assert_eq!(placed(&universe, "Rens gate"), (vec![], vec![]));
+    }
+
+    #[test]
+    fn structures_are_placed_in_the_system_named_before_them() {
+        let universe = universe();
+
+        assert_eq!(
+            placed(&universe, "Rens citadel, Gyng clear"),
+            (vec![place("Rens", "citadel")], vec![])
+        );
+    }
+
+    #[test]
+    fn structures_are_placed_in_the_only_system_mentioned() {
+        let universe = universe();
+
+        assert_eq!(
+            placed(&universe, "5 reds on citadel in Rens"),
+            (vec![place("Rens", "citadel")], vec![])
+        );
+        assert_eq!(placed(&universe, "Rens Gyng on citadel"), (vec![], vec![]));
+    }
+
+    #[test]
+    fn structures_are_not_placed_where_a_gate_leads() {
+        let universe = universe();
+
+        assert_eq!(
+            placed(&universe, "Gyng on Rens gate, tcu"),
+            (vec![place("Gyng", "Rens gate")], vec!["Rens".to_string()])
+        );
+    }
+}
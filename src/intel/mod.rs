@@ -1,18 +1,42 @@
 use super::chat;
+use super::config;
 use super::errors::*;
 use super::events;
 use super::universe;
 
+use chrono::prelude::*;
+use chrono::Duration;
 use regex;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::mpsc;
+use std::sync::Arc;
 
+mod board;
 mod message;
-pub use self::message::{Message, ThreatAssetment};
+mod players;
+mod tracker;
+use self::board::Board;
+pub use self::board::{Known, Status};
+pub use self::message::{Message, Place, ThreatAssetment};
+pub use self::players::{Evidence, Roster};
+use self::tracker::Tracker;
+
+/// Lines remembered to tell those heard by several characters apart.
+const HEARD_LINES: usize = 256;
+
+/// Jumps around a system whose state is told when somebody asks for its
+/// status.
+pub const STATUS_JUMPS: u16 = 2;
 
 pub struct Intel {
     locations: HashMap<String, universe::System>,
+    roster: Roster,
+    board: Board,
+    tracker: Tracker,
+    heard: VecDeque<(DateTime<Utc>, String, String, String)>,
     events: mpsc::Sender<events::Event>,
+    conf: config::Config,
+    universe: Arc<universe::Universe>,
 }
 
 lazy_static! {
@@ -21,26 +45,131 @@ lazy_static! {
 }
 
 impl Intel {
-    pub fn new(events: mpsc::Sender<events::Event>) -> Intel {
+    pub fn new(
+        events: mpsc::Sender<events::Event>,
+        conf: &config::Config,
+        universe: Arc<universe::Universe>,
+    ) -> Intel {
         Intel {
             locations: HashMap::new(),
+            roster: Roster::default(),
+            board: Board::new(decay(conf)),
+            tracker: Tracker::new(decay(conf)),
+            heard: VecDeque::new(),
             events: events,
+            conf: conf.clone(),
+            universe: universe,
         }
     }
 
-    pub fn intel_message(&self, message: chat::Message) -> Result<()> {
-        if let Some(location) = self.locations.get(&message.listener) {
-            if let Some(intel) = message::Message::new(message, &location) {
-                self.events.send(events::Event::IntelReport(intel))?;
+    pub fn reconfigure(&mut self, conf: &config::Config) {
+        self.conf = conf.clone();
+        self.board.set_decay(decay(conf));
+        self.tracker.set_decay(decay(conf));
+    }
+
+    /// Remembers who reported in a channel, so their names are not taken
+    /// for hostiles when they are mentioned in intel.
+    pub fn sender(&mut self, message: &chat::Message) {
+        self.roster.add_sender(&message.sender);
+    }
+
+    /// Assesses `message` for every tracked character whose location is
+    /// known and the channel applies to, whoever heard it. Lines heard by
+    /// several characters are only assessed once.
+    pub fn intel_message(&mut self, message: chat::Message) -> Result<()> {
+        self.sender(&message);
+        if !self.first_heard(&message) {
+            return Ok(());
+        }
+        let channel = match self.conf.find_channel(&message.channel) {
+            Some(channel) => channel.clone(),
+            None => return Ok(()),
+        };
+
+        let mut characters = self
+            .locations
+            .iter()
+            .filter(|&(character, _)| self.conf.players.contains(character))
+            .filter(|&(character, _)| channel.applies_to(character))
+            .map(|(character, location)| (character.clone(), location.clone()))
+            .collect::<Vec<_>>();
+        characters.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut reports = vec![];
+        for (character, location) in characters.into_iter() {
+            let mut heard = message.clone();
+            heard.listener = character;
+            let intel = message::Message::new(
+                heard,
+                &location,
+                &self.conf,
+                &channel,
+                &self.universe,
+                &self.roster,
+            );
+            if let Some(mut intel) = intel {
+                self.board.recall(&mut intel);
+                if intel.status == Status::Requested {
+                    intel.known = self.board.around(
+                        &self.universe,
+                        &self.conf.routing.intel,
+                        &intel.origin,
+                        STATUS_JUMPS,
+                        &intel.received_at,
+                    );
+                }
+                reports.push((intel, location));
+            }
+        }
+
+        for &(ref intel, _) in reports.iter() {
+            self.board.update(intel);
+            for player in intel.involved_players.iter() {
+                if player.evidence == Evidence::Link {
+                    self.roster.add(&player.name);
+                }
             }
         }
+        let moved = reports.first().and_then(|&(ref intel, _)| {
+            self.tracker
+                .follow(&self.universe, &self.conf.routing.intel, intel)
+        });
+        for (mut intel, location) in reports.into_iter() {
+            let approach = moved.as_ref().and_then(|moved| {
+                moved.approach(&self.universe, &self.conf.routing.intel, &location)
+            });
+            if let Some(approach) = approach {
+                let threat = self.conf.threat.for_player(&intel.player);
+                intel.approaching(approach, &threat);
+            }
+            self.events.send(events::Event::IntelReport(intel))?;
+        }
         Ok(())
     }
 
+    /// Whether `message` was not heard by another character already.
+    fn first_heard(&mut self, message: &chat::Message) -> bool {
+        let line = (
+            message.received_at,
+            message.channel.clone(),
+            message.sender.clone(),
+            message.message.clone(),
+        );
+        if self.heard.contains(&line) {
+            return false;
+        }
+        if self.heard.len() == HEARD_LINES {
+            self.heard.pop_front();
+        }
+        self.heard.push_back(line);
+        true
+    }
+
     pub fn location_message(&mut self, message: chat::Message) -> Result<()> {
         if let Some(tokens) = LOCATION_MESSAGE.captures(&message.message) {
             let name = tokens.get(1).ok_or("should match")?.as_str();
-            let system = universe::System::find(name).ok_or("no such system")?;
+            let system = self.universe.find(name).ok_or("no such system")?.clone();
 
             info!("{} is in {}", message.listener, system.name);
             self.locations.insert(message.listener.clone(), system);
@@ -48,3 +177,7 @@ impl Intel {
         Ok(())
     }
 }
+
+fn decay(conf: &config::Config) -> Duration {
+    Duration::minutes(conf.threat.decay as i64)
+}
@@ -0,0 +1,349 @@
+use super::universe;
+
+use regex;
+use std::collections::{HashMap, HashSet};
+
+/// Type ids of characters in `showinfo` links, one per bloodline.
+const CHARACTER_TYPES: [u32; 15] = [
+    1373, 1374, 1375, 1376, 1377, 1378, 1379, 1380, 1381, 1382, 1383, 1384, 1385, 1386, 34574,
+];
+/// Longest name a character may have.
+const MAX_NAME_LENGTH: usize = 37;
+const MAX_NAME_WORDS: usize = 3;
+/// Names are separated by two spaces when dragged out of local into a
+/// channel.
+const NAME_SEPARATOR: &'static str = "  ";
+
+lazy_static! {
+    /// Links pasted into chat, as `<url=showinfo:1378//90000001>Name</url>`
+    /// or `<a href="showinfo:1378//90000001">Name</a>`.
+    static ref LINK: regex::Regex = regex::Regex::new(
+        r#"(?i)<(?:url=|a href=")showinfo:(\d+)(?://(\d+))?"?>(.*?)</(?:url|a)>"#
+    ).expect("must compile");
+}
+
+/// Why a part of a line is taken for the name of a pilot, best first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Evidence {
+    /// A character link pasted into the line.
+    Link,
+    /// The name of a pilot linked in an earlier line.
+    Known,
+    /// Text set apart by two spaces, like names dragged out of local.
+    Spacing,
+}
+
+/// A pilot an intel line is about.
+#[derive(Clone, Debug)]
+pub struct Player {
+    pub name: String,
+    /// Character id, when the name was linked.
+    pub id: Option<String>,
+    pub evidence: Evidence,
+    /// How likely the name is the one of a pilot, between 0 and 1.
+    pub confidence: f64,
+}
+
+/// Names of the pilots linked so far, and of those seen reporting in a
+/// channel, who are friendly and never taken for hostiles.
+#[derive(Clone, Debug, Default)]
+pub struct Roster {
+    names: HashMap<String, String>,
+    senders: HashSet<String>,
+}
+
+impl Roster {
+    /// Remembers a pilot linked in a line.
+    pub fn add(&mut self, name: &str) {
+        let name = name.trim();
+        if !name.is_empty() {
+            self.names
+                .entry(name.to_uppercase())
+                .or_insert_with(|| name.to_string());
+        }
+    }
+
+    /// Remembers a pilot reporting in a channel.
+    pub fn add_sender(&mut self, name: &str) {
+        let name = name.trim();
+        if !name.is_empty() {
+            self.senders.insert(name.to_uppercase());
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&String> {
+        self.names.get(&name.to_uppercase())
+    }
+
+    fn is_sender(&self, name: &str) -> bool {
+        self.senders.contains(&name.to_uppercase())
+    }
+}
+
+/// A chat line with its links taken apart.
+pub struct Links {
+    /// The line as shown in game, links replaced by their text.
+    pub text: String,
+    /// The line without the names of linked characters, which are set apart
+    /// by two spaces instead.
+    pub content: String,
+    pub players: Vec<Player>,
+}
+
+pub fn links(line: &str) -> Links {
+    let players = LINK
+        .captures_iter(line)
+        .filter(|captures| is_character(captures))
+        .map(|captures| Player {
+            name: captures[3].trim().to_string(),
+            id: captures.get(2).map(|id| id.as_str().to_string()),
+            evidence: Evidence::Link,
+            confidence: confidence(Evidence::Link),
+        })
+        .filter(|player| !player.name.is_empty())
+        .collect();
+    let content = LINK.replace_all(line, |captures: &regex::Captures| {
+        if is_character(captures) {
+            NAME_SEPARATOR.to_string()
+        } else {
+            captures[3].to_string()
+        }
+    });
+
+    Links {
+        text: LINK.replace_all(line, "$3").into_owned(),
+        content: content.into_owned(),
+        players: players,
+    }
+}
+
+fn is_character(captures: &regex::Captures) -> bool {
+    captures[1]
+        .parse::<u32>()
+        .map(|kind| CHARACTER_TYPES.contains(&kind))
+        .unwrap_or(false)
+}
+
+/// Every pilot `links` names, once each and most certain first: the linked
+/// ones, those linked before according to `roster` and, when the line has
+/// names set apart by two spaces, whatever in between that is not a system,
+/// a ship or a number. Pilots seen reporting are only taken when linked.
+pub fn involved(universe: &universe::Universe, links: &Links, roster: &Roster) -> Vec<Player> {
+    let mut players = links.players.clone();
+    players.extend(known(&links.content, roster));
+    if links.content.contains(NAME_SEPARATOR) {
+        players.extend(
+            spaced(universe, &links.content)
+                .into_iter()
+                .map(|name| player(&name, Evidence::Spacing)),
+        );
+    }
+    players.retain(|player| player.evidence == Evidence::Link || !roster.is_sender(&player.name));
+
+    players.sort_by(|a, b| {
+        a.name
+            .to_uppercase()
+            .cmp(&b.name.to_uppercase())
+            .then_with(|| a.evidence.cmp(&b.evidence))
+    });
+    players.dedup_by(|a, b| a.name.to_uppercase() == b.name.to_uppercase());
+    players.sort_by(|a, b| {
+        a.evidence
+            .cmp(&b.evidence)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    players
+}
+
+/// Names in `roster` found in `text`, the longest where they overlap.
+fn known(text: &str, roster: &Roster) -> Vec<Player> {
+    let words = text
+        .split_whitespace()
+        .map(|word| word.trim_right_matches(|c: char| c.is_ascii_punctuation() && c != '.'))
+        .collect::<Vec<_>>();
+    let mut players = vec![];
+    let mut index = 0;
+
+    while index < words.len() {
+        let longest = (1..MAX_NAME_WORDS + 1)
+            .rev()
+            .filter(|length| index + length <= words.len())
+            .filter_map(|length| {
+                let name = roster.get(&words[index..index + length].join(" "))?;
+                Some((length, name))
+            })
+            .next();
+
+        match longest {
+            Some((length, name)) => {
+                players.push(player(name, Evidence::Known));
+                index += length;
+            }
+            None => index += 1,
+        }
+    }
+    players
+}
+
+/// Parts of `text` set apart by two spaces that may be names. The first
+/// part may start with the system the pilots are in and the last one end
+/// with it, as in `Niarja Bob Smith  Jane Doe`.
+fn spaced(universe: &universe::Universe, text: &str) -> Vec<String> {
+    let parts = text
+        .split(NAME_SEPARATOR)
+        .map(|part| part.trim())
+        .collect::<Vec<_>>();
+    let last = parts.len() - 1;
+
+    parts
+        .iter()
+        .enumerate()
+        .filter_map(|(index, part)| {
+            if is_name(universe, part) {
+                return Some(part.to_string());
+            }
+            let words = part.split_whitespace().collect::<Vec<_>>();
+            let name = if index == 0 {
+                let start = words.iter().rposition(|word| is_thing(universe, word))?;
+                words[start + 1..].join(" ")
+            } else if index == last {
+                let end = words.iter().position(|word| is_thing(universe, word))?;
+                words[..end].join(" ")
+            } else {
+                return None;
+            };
+            Some(name).filter(|name| is_name(universe, name))
+        })
+        .collect()
+}
+
+/// Whether `text` may be the name of a character: short enough, made of
+/// the characters names are made of, not only stop words and without
+/// systems, ships or numbers in it.
+fn is_name(universe: &universe::Universe, text: &str) -> bool {
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    if words.is_empty()
+        || words.len() > MAX_NAME_WORDS
+        || text.chars().count() > MAX_NAME_LENGTH
+        || !text
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '\'' || c == '.')
+    {
+        return false;
+    }
+    if universe.ship_exists(text) || words.iter().all(|word| universe.is_stop_word(word)) {
+        return false;
+    }
+    !words.iter().any(|word| is_thing(universe, word))
+}
+
+/// Whether `word` is a number, a system or a ship rather than a part of a
+/// name.
+fn is_thing(universe: &universe::Universe, word: &str) -> bool {
+    word.parse::<u32>().is_ok() || universe.find(word).is_some() || universe.ship_exists(word)
+}
+
+fn player(name: &str, evidence: Evidence) -> Player {
+    Player {
+        name: name.to_string(),
+        id: None,
+        evidence: evidence,
+        confidence: confidence(evidence),
+    }
+}
+
+fn confidence(evidence: Evidence) -> f64 {
+    match evidence {
+        Evidence::Link => 1.0,
+        Evidence::Known => 0.8,
+        Evidence::Spacing => 0.4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use universe::testing;
+
+    fn universe() -> universe::Universe {
+        testing::universe(
+            &["Niarja", "Kedama"],
+            &[("Niarja", "Kedama")],
+            &["IN", "GATE"],
+        )
+    }
+
+    fn named(line: &str, roster: &Roster) -> Vec<(String, Evidence)> {
+        involved(&universe(), &links(line), roster)
+            .into_iter()
+            .map(|player| (player.name, player.evidence))
+            .collect()
+    }
+
+    fn by_spacing(name: &str) -> (String, Evidence) {
+        (name.to_string(), Evidence::Spacing)
+    }
+
+    #[test]
+    fn takes_names_set_apart_after_the_system() {
+        let roster = Roster::default();
+
+        assert_eq!(
+            named("Niarja Bob Smith  Jane Doe  in gate", &roster),
+            vec![by_spacing("Bob Smith"), by_spacing("Jane Doe")]
+        );
+    }
+
+    #[test]
+    fn takes_names_set_apart_before_the_system() {
+        let roster = Roster::default();
+
+        assert_eq!(
+            named("Bob Smith  Jane Doe Kedama", &roster),
+            vec![by_spacing("Bob Smith"), by_spacing("Jane Doe")]
+        );
+    }
+
+    #[test]
+    fn takes_linked_names() {
+        let players = involved(
+            &universe(),
+            &links("<url=showinfo:1378//90000001>Ann Smith</url> Niarja"),
+            &Roster::default(),
+        );
+
+        assert_eq!(players.len(), 1);
+        assert_eq!(players[0].name, "Ann Smith");
+        assert_eq!(players[0].id, Some("90000001".to_string()));
+        assert_eq!(players[0].evidence, Evidence::Link);
+    }
+
+    #[test]
+    fn takes_names_linked_before() {
+        let mut roster = Roster::default();
+        roster.add("Ann Smith");
+
+        assert_eq!(
+            named("ann smith in Niarja", &roster),
+            vec![("Ann Smith".to_string(), Evidence::Known)]
+        );
+    }
+
+    #[test]
+    fn leaves_out_reporters_unless_linked() {
+        let mut roster = Roster::default();
+        roster.add_sender("Jane Doe");
+
+        assert_eq!(
+            named("Niarja Bob Smith  Jane Doe", &roster),
+            vec![by_spacing("Bob Smith")]
+        );
+        assert_eq!(
+            named(
+                "<url=showinfo:1378//90000002>Jane Doe</url> Niarja",
+                &roster
+            ),
+            vec![("Jane Doe".to_string(), Evidence::Link)]
+        );
+    }
+}
@@ -0,0 +1,415 @@
+use super::board::Status;
+use super::message::Message;
+use super::universe;
+
+use chrono::prelude::*;
+use chrono::Duration;
+
+/// Farthest a gang known only by its ships is taken to have moved between
+/// two reports.
+const MAX_GANG_JUMPS: u16 = 3;
+/// Least confidence in the name of a pilot for gangs to be told apart by
+/// it: names linked in the line or an earlier one, not text only set apart
+/// by spacing.
+const MIN_CONFIDENCE: f64 = 0.8;
+
+/// A gang moving from one system to another, as of one report.
+#[derive(Clone, Debug)]
+pub struct Move {
+    pub from: universe::System,
+    pub to: universe::System,
+}
+
+impl Move {
+    /// Whether the move brought the gang closer to `location`.
+    pub fn approach(
+        &self,
+        universe: &universe::Universe,
+        options: &universe::RouteOptions,
+        location: &universe::System,
+    ) -> Option<Approach> {
+        let jumps_before = universe::distance(universe, &self.from, location, options)?;
+        let jumps = universe::distance(universe, &self.to, location, options)?;
+        if jumps >= jumps_before {
+            return None;
+        }
+        Some(Approach {
+            from: self.from.clone(),
+            to: self.to.clone(),
+            jumps_before: jumps_before,
+            jumps: jumps,
+        })
+    }
+}
+
+/// A gang moving closer to the listener.
+#[derive(Clone, Debug)]
+pub struct Approach {
+    pub from: universe::System,
+    pub to: universe::System,
+    /// Jumps from the listener before and after the move.
+    pub jumps_before: u16,
+    pub jumps: u16,
+}
+
+/// Hostiles reported together, known by the pilots and ships reported.
+struct Gang {
+    players: Vec<String>,
+    ships: Vec<String>,
+    /// Systems the gang was reported in, the last one being where it is.
+    trail: Vec<universe::System>,
+    seen_at: DateTime<Utc>,
+}
+
+impl Gang {
+    fn is_in(&self, system: &universe::System) -> bool {
+        self.trail
+            .last()
+            .map(|last| last.id == system.id)
+            .unwrap_or(false)
+    }
+
+    fn has_any(&self, players: &[String]) -> bool {
+        players.iter().any(|player| contains(&self.players, player))
+    }
+
+    /// Whether most of the smaller of both fleets is in the other.
+    fn flies(&self, ships: &[String]) -> bool {
+        let shared = ships
+            .iter()
+            .filter(|ship| contains(&self.ships, ship))
+            .count();
+        shared > 0 && shared * 2 >= ships.len().min(self.ships.len())
+    }
+
+    fn add(&mut self, players: Vec<String>, ships: Vec<String>) {
+        for player in players {
+            if !contains(&self.players, &player) {
+                self.players.push(player);
+            }
+        }
+        for ship in ships {
+            if !contains(&self.ships, &ship) {
+                self.ships.push(ship);
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        if self.players.is_empty() {
+            self.ships.join(", ")
+        } else {
+            self.players.join(", ")
+        }
+    }
+}
+
+fn contains(names: &[String], name: &str) -> bool {
+    names
+        .iter()
+        .any(|known| known.to_uppercase() == name.to_uppercase())
+}
+
+/// Gangs followed from one report to the next, by the pilots reported or,
+/// failing that, by their ships. Each is forgotten `decay` after the last
+/// report about it.
+pub struct Tracker {
+    gangs: Vec<Gang>,
+    decay: Duration,
+}
+
+impl Tracker {
+    pub fn new(decay: Duration) -> Tracker {
+        Tracker {
+            gangs: vec![],
+            decay: decay,
+        }
+    }
+
+    pub fn set_decay(&mut self, decay: Duration) {
+        self.decay = decay;
+    }
+
+    /// Follows the gang `message` reports hostiles of, and tells where it
+    /// moved if `message` is the report it moved as of.
+    pub fn follow(
+        &mut self,
+        universe: &universe::Universe,
+        options: &universe::RouteOptions,
+        message: &Message,
+    ) -> Option<Move> {
+        if message.status != Status::Hostile {
+            return None;
+        }
+        let now = message.received_at;
+        let decay = self.decay;
+        self.gangs
+            .retain(|gang| now.signed_duration_since(gang.seen_at) < decay);
+
+        let players = message
+            .involved_players
+            .iter()
+            .filter(|player| player.confidence >= MIN_CONFIDENCE)
+            .map(|player| player.name.clone())
+            .collect::<Vec<_>>();
+        let ships = message
+            .ships
+            .iter()
+            .map(|ship| ship.name.clone())
+            .collect::<Vec<_>>();
+        if players.is_empty() && ships.is_empty() {
+            return None;
+        }
+        let system = match message.movement {
+            Some(ref movement) => &movement.to,
+            None => &message.origin,
+        };
+
+        let index = self
+            .gangs
+            .iter()
+            .position(|gang| gang.has_any(&players))
+            .or_else(|| {
+                self.gangs.iter().position(|gang| {
+                    gang.flies(&ships)
+                        && gang
+                            .trail
+                            .last()
+                            .and_then(|last| universe::distance(universe, last, system, options))
+                            .map(|jumps| jumps <= MAX_GANG_JUMPS)
+                            .unwrap_or(false)
+                })
+            });
+        let gang = match index {
+            Some(index) => &mut self.gangs[index],
+            None => {
+                let trail = message
+                    .movement
+                    .as_ref()
+                    .and_then(|movement| movement.from.clone())
+                    .into_iter()
+                    .collect();
+                self.gangs.push(Gang {
+                    players: vec![],
+                    ships: vec![],
+                    trail: trail,
+                    seen_at: now,
+                });
+                self.gangs.last_mut()?
+            }
+        };
+
+        gang.add(players, ships);
+        gang.seen_at = now;
+        if gang.is_in(system) {
+            return None;
+        }
+        gang.trail.push(system.clone());
+        if gang.trail.len() < 2 {
+            return None;
+        }
+
+        let from = gang.trail[gang.trail.len() - 2].clone();
+        info!(
+            "{} moved from {} to {}",
+            gang.describe(),
+            from.name,
+            system.name
+        );
+        Some(Move {
+            from: from,
+            to: system.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intel::players::{Evidence, Player};
+    use universe::testing;
+
+    /// Home, then a line of systems One to Four away from it.
+    fn universe() -> universe::Universe {
+        testing::universe(
+            &["Home", "One", "Two", "Three", "Four"],
+            &[
+                ("Home", "One"),
+                ("One", "Two"),
+                ("Two", "Three"),
+                ("Three", "Four"),
+            ],
+            &[],
+        )
+    }
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        Utc.ymd(2018, 1, 1).and_hms(12, 0, 0) + Duration::minutes(minutes)
+    }
+
+    fn pilot(name: &str, evidence: Evidence, confidence: f64) -> Player {
+        Player {
+            name: name.to_string(),
+            id: None,
+            evidence: evidence,
+            confidence: confidence,
+        }
+    }
+
+    fn ship(name: &str) -> universe::ShipType {
+        universe::ShipType {
+            name: name.to_string(),
+            class: String::new(),
+            hull: String::new(),
+        }
+    }
+
+    /// Report of `players` in `system`, `minutes` after the first one.
+    fn report(
+        universe: &universe::Universe,
+        system: &str,
+        players: Vec<Player>,
+        minutes: i64,
+    ) -> Message {
+        let mut message = Message::report(universe.find(system).unwrap(), at(minutes));
+        message.involved_players = players;
+        message
+    }
+
+    fn follow(
+        tracker: &mut Tracker,
+        universe: &universe::Universe,
+        message: &Message,
+    ) -> Option<Move> {
+        tracker.follow(universe, &universe::RouteOptions::default(), message)
+    }
+
+    #[test]
+    fn follows_gangs_by_linked_pilots() {
+        let universe = universe();
+        let mut tracker = Tracker::new(Duration::minutes(10));
+        let linked = || vec![pilot("Ann Smith", Evidence::Link, 1.0)];
+
+        assert!(follow(
+            &mut tracker,
+            &universe,
+            &report(&universe, "Four", linked(), 0)
+        )
+        .is_none());
+        let moved = follow(
+            &mut tracker,
+            &universe,
+            &report(&universe, "Two", linked(), 1),
+        )
+        .unwrap();
+
+        assert_eq!(moved.from.name, "Four");
+        assert_eq!(moved.to.name, "Two");
+    }
+
+    #[test]
+    fn does_not_follow_gangs_by_names_only_set_apart() {
+        let universe = universe();
+        let mut tracker = Tracker::new(Duration::minutes(10));
+        let spaced = || vec![pilot("gang", Evidence::Spacing, 0.4)];
+
+        follow(
+            &mut tracker,
+            &universe,
+            &report(&universe, "Four", spaced(), 0),
+        );
+
+        assert!(follow(
+            &mut tracker,
+            &universe,
+            &report(&universe, "One", spaced(), 1)
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn follows_gangs_by_ships_nearby_only() {
+        let universe = universe();
+        let mut tracker = Tracker::new(Duration::minutes(10));
+        let flying = |system, minutes| {
+            let mut message = report(&universe, system, vec![], minutes);
+            message.ships = vec![ship("Sabre"), ship("Loki")];
+            message
+        };
+
+        follow(&mut tracker, &universe, &flying("Home", 0));
+        assert!(follow(&mut tracker, &universe, &flying("Four", 1)).is_none());
+        let moved = follow(&mut tracker, &universe, &flying("One", 2)).unwrap();
+
+        assert_eq!(moved.from.name, "Home");
+        assert_eq!(moved.to.name, "One");
+    }
+
+    #[test]
+    fn forgets_gangs_after_decay() {
+        let universe = universe();
+        let mut tracker = Tracker::new(Duration::minutes(10));
+        let linked = || vec![pilot("Ann Smith", Evidence::Link, 1.0)];
+
+        follow(
+            &mut tracker,
+            &universe,
+            &report(&universe, "Four", linked(), 0),
+        );
+
+        assert!(follow(
+            &mut tracker,
+            &universe,
+            &report(&universe, "Two", linked(), 10)
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn moves_only_as_of_the_report_moving_the_gang() {
+        let universe = universe();
+        let mut tracker = Tracker::new(Duration::minutes(10));
+        let linked = || vec![pilot("Ann Smith", Evidence::Link, 1.0)];
+
+        follow(
+            &mut tracker,
+            &universe,
+            &report(&universe, "Four", linked(), 0),
+        );
+        assert!(follow(
+            &mut tracker,
+            &universe,
+            &report(&universe, "Two", linked(), 0)
+        )
+        .is_some());
+
+        assert!(follow(
+            &mut tracker,
+            &universe,
+            &report(&universe, "Two", linked(), 0)
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn approaches_only_when_closer() {
+        let universe = universe();
+        let options = universe::RouteOptions::default();
+        let system = |name| universe.find(name).unwrap().clone();
+        let moved = Move {
+            from: system("Three"),
+            to: system("Two"),
+        };
+
+        let approach = moved
+            .approach(&universe, &options, &system("Home"))
+            .unwrap();
+        assert_eq!((approach.jumps_before, approach.jumps), (3, 2));
+        assert!(moved
+            .approach(&universe, &options, &system("Four"))
+            .is_none());
+        assert!(moved
+            .approach(&universe, &options, &system("Two"))
+            .is_some());
+    }
+}
@@ -1,10 +1,12 @@
 mod app;
 mod chat;
+mod cli;
 mod config;
 mod errors;
 mod events;
 mod intel;
 mod notifications;
+mod sde;
 mod universe;
 
 #[macro_use]
@@ -17,17 +19,25 @@ extern crate lazy_static;
 extern crate serde_derive;
 
 extern crate chrono;
+extern crate clap;
 extern crate encoding;
 extern crate fern;
 extern crate memmap;
 extern crate notify;
 extern crate regex;
+extern crate serde;
+extern crate serde_json;
+extern crate serde_yaml;
+extern crate strsim;
+extern crate toml;
 
 use errors::*;
 use fern::colors::ColoredLevelConfig;
+use std::sync::Arc;
 quick_main!(run);
 
 fn run() -> Result<()> {
+    let cli = cli::Cli::new();
     let colors = ColoredLevelConfig::default();
 
     fern::Dispatch::new()
@@ -40,18 +50,76 @@ fn run() -> Result<()> {
                 message
             ))
         })
-        .level(log::LevelFilter::Debug)
+        .level(cli.log_level()?)
         .chain(std::io::stdout())
         .apply()?;
 
-    let conf = config::Config::default()?
-        .player("Derzerek")
-        .player("Yolla")
-        .player("Inge Inkura")
-        .channel("GotG Home Intel")
-        .channel("Derzerek");
-
-    info!("Starting the app");
-    app::run(conf)?;
+    match cli.command() {
+        // builds the universe, so it needs neither the universe nor the
+        // configuration resolved against it
+        cli::Command::Import {
+            sde,
+            data,
+            output,
+            build,
+            wormhole_space,
+            update_ships,
+        } => app::import(&sde, &data, &output, build, wormhole_space, update_ships)?,
+        cli::Command::Watch => {
+            let (conf, universe) = load(&cli)?;
+            info!("Starting the app");
+            app::run(conf.validate()?, universe)?;
+        }
+        cli::Command::Route(from, to, avoid, preference) => {
+            let (conf, universe) = load(&cli)?;
+            let mut options = conf.routing.travel.clone();
+            if let Some(preference) = preference {
+                options.preference = preference;
+            }
+            options.avoid.systems.extend(avoid.systems);
+            options.avoid.constellations.extend(avoid.constellations);
+            options.avoid.regions.extend(avoid.regions);
+            app::route(&universe, &from, &to, &options)?;
+        }
+        cli::Command::Parse(location, line) => {
+            let (conf, universe) = load(&cli)?;
+            app::parse(&conf, &universe, &location, &line)?
+        }
+        cli::Command::Wormhole(command) => {
+            let (conf, universe) = load(&cli)?;
+            match command {
+                cli::WormholeCommand::Add(from, to, hours) => {
+                    app::add_wormhole(&conf, &universe, &from, &to, hours)?
+                }
+                cli::WormholeCommand::Remove(from, to) => {
+                    app::remove_wormhole(&conf, &universe, &from, &to)?
+                }
+                cli::WormholeCommand::List => app::list_wormholes(&conf)?,
+            }
+        }
+        cli::Command::Bench(from, rounds) => {
+            let (conf, universe) = load(&cli)?;
+            app::bench(&conf, &universe, &from, rounds)?
+        }
+        cli::Command::Replay(logdir) => {
+            let (mut conf, universe) = load(&cli)?;
+            conf.chat_logs = logdir;
+            app::replay(conf.validate()?, universe)?;
+        }
+    }
     Ok(())
 }
+
+/// The configuration, resolved against the universe it names along with
+/// the aliases it adds.
+fn load(cli: &cli::Cli) -> Result<(config::Config, Arc<universe::Universe>)> {
+    let conf = cli.config()?;
+    let mut universe = universe::load(conf.universe_path())?;
+    for file in conf.alias_paths() {
+        let count = universe.add_aliases(&file)?;
+        info!("Loaded {} aliases from {}", count, file.display());
+    }
+    let universe = Arc::new(universe);
+    let conf = conf.resolve(&universe)?;
+    Ok((conf, universe))
+}
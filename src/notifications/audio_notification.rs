@@ -32,13 +32,17 @@ pub struct AudioNotification {
 }
 
 impl AudioNotification {
-    pub fn new() -> AudioNotification {
+    pub fn new(voice: &str) -> AudioNotification {
         AudioNotification {
-            tts: TTSService::new(),
+            tts: TTSService::new(voice),
             sounds: sound_loop(),
         }
     }
 
+    pub fn set_voice(&mut self, voice: &str) {
+        self.tts.voice = voice.to_string();
+    }
+
     pub fn notify(&self, text: &str) -> Option<()> {
         let sound = {
             let buf = self.tts.synthesize(text)?;
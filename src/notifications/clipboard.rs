@@ -0,0 +1,29 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Pipes `text` into `command`, e.g. `xclip -selection clipboard`.
+pub fn copy(command: &str, text: &str) {
+    let mut words = command.split_whitespace();
+    let program = match words.next() {
+        Some(program) => program,
+        None => return,
+    };
+    let child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn();
+
+    match child {
+        Ok(mut child) => {
+            if let Some(mut stdin) = child.stdin.take() {
+                if let Err(error) = stdin.write_all(text.as_bytes()) {
+                    error!("unable to copy with {}: {}", command, error);
+                }
+            }
+            if let Err(error) = child.wait() {
+                error!("unable to copy with {}: {}", command, error);
+            }
+        }
+        Err(error) => error!("unable to run {}: {}", command, error),
+    }
+}
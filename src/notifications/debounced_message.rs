@@ -1,4 +1,6 @@
 use super::audio_notification::AudioNotification;
+use super::clipboard;
+use super::config;
 use super::desktop_notification::desktop_notification;
 use super::intel;
 use super::Notification;
@@ -16,17 +18,27 @@ impl DebouncedMessage {
     pub fn new(message: intel::Message) -> DebouncedMessage {
         DebouncedMessage(message)
     }
+
+    /// The character an alert is for, none for clear reports and status
+    /// requests which read the same for everyone.
+    fn player(&self) -> Option<&String> {
+        match self.0.threat_assement {
+            intel::ThreatAssetment::NoThreat(_) | intel::ThreatAssetment::StatusRequest(_) => None,
+            _ => Some(&self.0.player),
+        }
+    }
 }
 
 impl Hash for DebouncedMessage {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.0.message.hash(state);
+        self.player().hash(state);
     }
 }
 
 impl PartialEq for DebouncedMessage {
     fn eq(&self, other: &DebouncedMessage) -> bool {
-        self.0.message == other.0.message
+        self.0.message == other.0.message && self.player() == other.player()
     }
 }
 
@@ -34,14 +46,16 @@ impl Eq for DebouncedMessage {}
 
 pub enum DebounceMessages {
     Intel(intel::Message),
+    Settings(config::NotificationSettings),
     Tick,
 }
 
 impl DebounceMessages {
-    pub fn channel() -> mpsc::Sender<DebounceMessages> {
+    pub fn channel(settings: config::NotificationSettings) -> mpsc::Sender<DebounceMessages> {
         let (tx, rx) = mpsc::channel();
         let tick = tx.clone();
-        let audio = AudioNotification::new();
+        let mut settings = settings;
+        let mut audio = AudioNotification::new(&settings.voice);
         let queue: Arc<Mutex<HashSet<DebouncedMessage>>> = Arc::new(Mutex::new(HashSet::new()));
         let q = queue.clone();
         thread::spawn(move || loop {
@@ -60,7 +74,7 @@ impl DebounceMessages {
                     {
                         let mut q = queue.lock().unwrap();
                         let message = if let Some(existing) = q.take(&debounced) {
-                            if existing.0.route.distance > debounced.0.route.distance {
+                            if existing.0.jumps > debounced.0.jumps {
                                 existing
                             } else {
                                 debounced
@@ -71,17 +85,24 @@ impl DebounceMessages {
                         q.insert(message);
                     }
                 }
+                Ok(DebounceMessages::Settings(new_settings)) => {
+                    audio.set_voice(&new_settings.voice);
+                    settings = new_settings;
+                }
                 Ok(DebounceMessages::Tick) => {
                     let mut q = queue.lock().unwrap();
                     for message in q.drain() {
                         let notification = Notification::from(message.0.clone());
+                        copy_answer(&settings, &message.0, &notification);
                         match notification {
-                            Notification::Sound(text) => {
+                            Notification::Sound(text) => if settings.audio {
                                 audio.notify(&text);
-                            }
-                            Notification::Desktop(text) => {
+                            } else if settings.desktop {
                                 desktop_notification(&text, &message.0.message);
-                            }
+                            },
+                            Notification::Desktop(text) => if settings.desktop {
+                                desktop_notification(&text, &message.0.message);
+                            },
                             Notification::None => {}
                         };
                     }
@@ -96,3 +117,21 @@ impl DebounceMessages {
         tx
     }
 }
+
+/// Copies the answer to a status request with the configured command, if
+/// any.
+fn copy_answer(
+    settings: &config::NotificationSettings,
+    message: &intel::Message,
+    notification: &Notification,
+) {
+    let command = match settings.clipboard {
+        Some(ref command) => command,
+        None => return,
+    };
+    if let (&intel::ThreatAssetment::StatusRequest(_), &Notification::Desktop(ref text)) =
+        (&message.threat_assement, notification)
+    {
+        clipboard::copy(command, text);
+    }
+}
@@ -1,8 +1,10 @@
+use super::config;
 use super::errors::*;
 use super::intel;
 use super::universe;
 
 mod audio_notification;
+mod clipboard;
 pub mod debounced_message;
 mod desktop_notification;
 mod tts;
@@ -14,9 +16,9 @@ pub struct Notifications {
 }
 
 impl Notifications {
-    pub fn new() -> Notifications {
+    pub fn new(settings: &config::NotificationSettings) -> Notifications {
         Notifications {
-            debouncer: debounced_message::DebounceMessages::channel(),
+            debouncer: debounced_message::DebounceMessages::channel(settings.clone()),
         }
     }
 
@@ -25,6 +27,14 @@ impl Notifications {
             .send(debounced_message::DebounceMessages::Intel(message))?;
         Ok(())
     }
+
+    pub fn reconfigure(&self, settings: &config::NotificationSettings) -> Result<()> {
+        self.debouncer
+            .send(debounced_message::DebounceMessages::Settings(
+                settings.clone(),
+            ))?;
+        Ok(())
+    }
 }
 
 pub enum Notification {
@@ -35,38 +45,224 @@ pub enum Notification {
 
 impl From<intel::Message> for Notification {
     fn from(message: intel::Message) -> Notification {
+        let audio = message.channel.audio;
+        match Notification::assess(message) {
+            Notification::Sound(text) if !audio => Notification::Desktop(text),
+            notification => notification,
+        }
+    }
+}
+
+impl Notification {
+    fn assess(message: intel::Message) -> Notification {
         match message.threat_assement {
-            intel::ThreatAssetment::NoThreat(system) => {
-                Notification::Sound(format!("{} is clear", human_system(&system)))
-            }
-            intel::ThreatAssetment::ProximityAlertCritical(_) => {
-                Notification::Sound(format!("{} threat in local DOCKDOCKDOCK", message.player))
+            intel::ThreatAssetment::NoThreat(system) => match message.previous {
+                Some(intel::Status::Hostile) | Some(intel::Status::Requested) => {
+                    Notification::Sound(format!("{} is clear", human_system(&system)))
+                }
+                _ => {
+                    info!("{} is clear", system.name);
+                    Notification::None
+                }
+            },
+            intel::ThreatAssetment::ProximityAlertCritical(0, _) => Notification::Sound(format!(
+                "{} {} in local{} DOCKDOCKDOCK{}",
+                message.player,
+                spoken_threat(message.hostiles),
+                place(&message, human_system),
+                movement(&message, human_system)
+            )),
+            intel::ThreatAssetment::ProximityAlertCritical(jumps, _) => {
+                Notification::Sound(format!(
+                    "{} {} {} away in {}{} DOCKDOCKDOCK{}",
+                    message.player,
+                    spoken_threat(message.hostiles),
+                    plural(&spoken(jumps as u32), jumps as u32, "jump"),
+                    human_system(&message.origin),
+                    place(&message, human_system),
+                    movement(&message, human_system)
+                ))
             }
-            intel::ThreatAssetment::ProximityAlertHigh(jumps) => Notification::Sound(format!(
-                "Threat {} jumps away from {} in {}",
-                jumps,
+            intel::ThreatAssetment::ProximityAlertHigh(jumps, _) => Notification::Sound(format!(
+                "{} {} away from {} in {}{}{}",
+                capitalize(&spoken_threat(message.hostiles)),
+                plural(&spoken(jumps as u32), jumps as u32, "jump"),
                 message.player,
-                human_system(&message.origin)
+                human_system(&message.origin),
+                place(&message, human_system),
+                movement(&message, human_system)
             )),
-            intel::ThreatAssetment::ProximityAlertLow(jumps) => {
+            intel::ThreatAssetment::ProximityAlertLow(jumps, ref band) => {
+                let threat = match message.hostiles {
+                    Some(hostiles) => plural(&hostiles.to_string(), hostiles, "hostile"),
+                    None => "Threat".to_string(),
+                };
                 let text = format!(
-                    "Threat {} jumps away from {} in {}",
-                    jumps, message.player, message.origin.name
+                    "[{}] {} {} away from {} in {}{}{}",
+                    band.name,
+                    threat,
+                    plural(&jumps.to_string(), jumps as u32, "jump"),
+                    message.player,
+                    message.origin.name,
+                    place(&message, |system| system.name.clone()),
+                    movement(&message, |system| system.name.clone())
                 );
                 warn!("{}", text);
                 Notification::Desktop(text)
             }
             intel::ThreatAssetment::ProximityIrelevant(_jumps) => Notification::None,
-            intel::ThreatAssetment::StatusRequest(system) => {
-                let text = format!("status request in {}", system.name);
-                warn!("{}", text);
-                Notification::None
+            intel::ThreatAssetment::StatusRequest(ref system) => {
+                let text = answer(system, &message);
+                info!("status request in {}: {}", system.name, text);
+                Notification::Desktop(text)
             }
-            intel::ThreatAssetment::Unknown => {
-                error!("Unable to asses threat level for {}", message.message);
-                Notification::None
+        }
+    }
+}
+
+/// Where the hostiles of an alert are going or coming from, if the report
+/// says so or they were seen closing in on the listener.
+fn movement<F>(message: &intel::Message, name: F) -> String
+where
+    F: Fn(&universe::System) -> String,
+{
+    if let Some(ref movement) = message.movement {
+        if movement.to.id != message.origin.id {
+            return format!(", moving to {}", name(&movement.to));
+        }
+        if let Some(ref from) = movement.from {
+            return format!(", coming from {}", name(from));
+        }
+    }
+    match message.approach {
+        Some(ref approach) => format!(", approaching from {}", name(&approach.from)),
+        None => String::new(),
+    }
+}
+
+/// Where in the system of an alert the hostiles are, if the report says so,
+/// and whether that is the gate the listener would come in through.
+fn place<F>(message: &intel::Message, name: F) -> String
+where
+    F: Fn(&universe::System) -> String,
+{
+    let place = match message.place {
+        Some(intel::Place::Gate(ref gate)) => format!(" on the {} gate", name(gate)),
+        Some(intel::Place::Structure(ref kind)) => format!(" at the {}", kind),
+        None => return String::new(),
+    };
+    if message.on_route {
+        format!("{} on your route", place)
+    } else {
+        place
+    }
+}
+
+/// What is known around the system a status was asked for and how long
+/// ago it was reported, short enough to be pasted in a channel.
+fn answer(system: &universe::System, message: &intel::Message) -> String {
+    if message.known.is_empty() {
+        return format!(
+            "{}: nothing reported within {} jumps",
+            system.name,
+            intel::STATUS_JUMPS
+        );
+    }
+    let mut parts = match message.known.first() {
+        Some(known) if known.jumps == 0 => vec![],
+        _ => vec![format!("{}: nothing reported", system.name)],
+    };
+    parts.extend(message.known.iter().map(describe_known));
+    parts.join(", ")
+}
+
+fn describe_known(known: &intel::Known) -> String {
+    let state = &known.state;
+    let what = match state.status {
+        intel::Status::Hostile => {
+            let hostiles = match state.hostiles {
+                Some(hostiles) => plural(&hostiles.to_string(), hostiles, "hostile"),
+                None => "hostiles".to_string(),
+            };
+            let ships = state
+                .ships
+                .iter()
+                .map(|ship| ship.name.clone())
+                .collect::<Vec<_>>();
+            match ships.len() {
+                0 => hostiles,
+                _ => format!("{} ({})", hostiles, ships.join(", ")),
             }
         }
+        _ => "clear".to_string(),
+    };
+    let ago = match known.age.num_minutes() {
+        0 => "just now".to_string(),
+        minutes => format!("{} min ago", minutes),
+    };
+    match known.jumps {
+        0 => format!("{}: {} {}", state.system.name, what, ago),
+        jumps => format!(
+            "{} ({}): {} {}",
+            state.system.name,
+            plural(&jumps.to_string(), jumps as u32, "jump"),
+            what,
+            ago
+        ),
+    }
+}
+
+/// "fifteen hostiles" when the report says how many, "threat" otherwise.
+fn spoken_threat(hostiles: Option<u32>) -> String {
+    match hostiles {
+        Some(hostiles) => plural(&spoken(hostiles), hostiles, "hostile"),
+        None => "threat".to_string(),
+    }
+}
+
+fn plural(number: &str, count: u32, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", number, noun)
+    } else {
+        format!("{} {}s", number, noun)
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+const ONES: [&'static str; 20] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen",
+    "nineteen",
+];
+const TENS: [&'static str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+/// `number` in words, so it reads the same with every voice. Numbers past
+/// the hundreds are left as digits.
+fn spoken(number: u32) -> String {
+    match number {
+        0..=19 => ONES[number as usize].to_string(),
+        20..=99 if number % 10 == 0 => TENS[(number / 10) as usize].to_string(),
+        20..=99 => format!(
+            "{} {}",
+            TENS[(number / 10) as usize],
+            ONES[(number % 10) as usize]
+        ),
+        100..=999 if number % 100 == 0 => format!("{} hundred", ONES[(number / 100) as usize]),
+        100..=999 => format!(
+            "{} hundred {}",
+            ONES[(number / 100) as usize],
+            spoken(number % 100)
+        ),
+        _ => number.to_string(),
     }
 }
 
@@ -11,6 +11,7 @@ use self::xml::writer::{EmitterConfig, XmlEvent};
 
 pub struct TTSService {
     aws: PollyClient,
+    pub voice: String,
     rate: &'static str,
     brain: TrainingData,
 }
@@ -20,9 +21,10 @@ const PROSODY_TAG: &'static str = "prosody";
 const MAX_LENGTH: usize = 1500;
 
 impl TTSService {
-    pub fn new() -> TTSService {
+    pub fn new(voice: &str) -> TTSService {
         TTSService {
             aws: PollyClient::simple(Region::EuCentral1),
+            voice: voice.to_string(),
             rate: "fast",
             brain: TrainingData::english(),
         }
@@ -30,7 +32,7 @@ impl TTSService {
 
     pub fn synthesize(&self, text: &str) -> Option<Vec<u8>> {
         let mut input = SynthesizeSpeechInput::default();
-        input.voice_id = "Salli".to_string();
+        input.voice_id = self.voice.clone();
         input.output_format = "ogg_vorbis".to_string();
         input.text_type = Some("ssml".to_string());
         let mut parts: Vec<String> = vec![];
@@ -0,0 +1,258 @@
+use super::errors::*;
+use super::universe;
+
+use serde::de::DeserializeOwned;
+use serde_yaml;
+use std::collections::HashMap;
+use std::fs;
+use std::path;
+
+/// Directories below `fsd/universe` holding the solar systems of known space
+/// and of wormhole space, Thera and J-space.
+const KNOWN_SPACE: &'static str = "eve";
+const WORMHOLE_SPACE: &'static str = "wormhole";
+const SOLAR_SYSTEM_FILE: &'static str = "solarsystem.staticdata";
+/// Written by exports that name the build they were made from.
+const BUILD_FILE: &'static str = "_sde.yaml";
+/// Root of the market groups ships are sold in.
+const SHIPS_MARKET_GROUP: u64 = 4;
+
+#[derive(Deserialize)]
+struct Build {
+    #[serde(rename = "buildNumber")]
+    number: i64,
+}
+
+#[derive(Deserialize)]
+struct SolarSystem {
+    #[serde(rename = "solarSystemID")]
+    id: u64,
+    security: Option<f64>,
+    #[serde(default)]
+    stargates: HashMap<u64, Stargate>,
+}
+
+#[derive(Deserialize)]
+struct Stargate {
+    destination: u64,
+}
+
+#[derive(Deserialize)]
+struct Type {
+    name: Option<Name>,
+    #[serde(rename = "groupID")]
+    group: Option<u64>,
+    #[serde(rename = "marketGroupID")]
+    market_group: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct Group {
+    name: Option<Name>,
+}
+
+#[derive(Deserialize)]
+struct MarketGroup {
+    #[serde(rename = "marketGroupID")]
+    id: u64,
+    #[serde(rename = "parentGroupID")]
+    parent: Option<u64>,
+    #[serde(rename = "marketGroupName")]
+    name: Option<Name>,
+    #[serde(rename = "nameID")]
+    name_id: Option<Name>,
+}
+
+/// Names are translated in newer exports and plain in older ones.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Name {
+    Plain(String),
+    Translated(HashMap<String, String>),
+}
+
+impl Name {
+    fn english(&self) -> Option<&String> {
+        match *self {
+            Name::Plain(ref name) => Some(name),
+            Name::Translated(ref names) => names.get("en"),
+        }
+    }
+}
+
+/// Build number of the export, if it records one.
+pub fn build(sde: &path::Path) -> Result<Option<i64>> {
+    let file = sde.join(BUILD_FILE);
+    if !file.is_file() {
+        return Ok(None);
+    }
+    let build: Build = load(&file)?;
+    Ok(Some(build.number))
+}
+
+/// Reads every solar system of known space in the static data export
+/// unpacked at `sde`, and those of wormhole space with `wormhole_space`.
+/// Names come from the `region/constellation/system` directory layout.
+pub fn systems(sde: &path::Path, wormhole_space: bool) -> Result<Vec<universe::System>> {
+    let mut systems = vec![];
+    let mut gates = HashMap::new();
+    let mut destinations = HashMap::new();
+    let spaces = if wormhole_space {
+        vec![KNOWN_SPACE, WORMHOLE_SPACE]
+    } else {
+        vec![KNOWN_SPACE]
+    };
+
+    for space in spaces.iter() {
+        let root = sde.join("fsd/universe").join(space);
+        if !root.is_dir() {
+            continue;
+        }
+        for region in dirs(&root)? {
+            for constellation in dirs(&region)? {
+                for dir in dirs(&constellation)? {
+                    let system: SolarSystem = load(&dir.join(SOLAR_SYSTEM_FILE))?;
+                    let id = system.id.to_string();
+
+                    for (gate, stargate) in system.stargates.iter() {
+                        gates.insert(*gate, id.clone());
+                        destinations
+                            .entry(id.clone())
+                            .or_insert_with(Vec::new)
+                            .push(stargate.destination);
+                    }
+
+                    systems.push(universe::System {
+                        id: id,
+                        name: name(&dir)?,
+                        constelation: name(&constellation)?,
+                        region: name(&region)?,
+                        neighbours: vec![],
+                        security: system.security,
+                    });
+                }
+            }
+        }
+    }
+
+    for system in systems.iter_mut() {
+        if let Some(destinations) = destinations.get(&system.id) {
+            system.neighbours = destinations
+                .iter()
+                .filter_map(|gate| gates.get(gate).cloned())
+                .collect();
+        }
+    }
+
+    if systems.is_empty() {
+        bail!("no solar systems found in {}", sde.display());
+    }
+    Ok(systems)
+}
+
+/// Every type sold in the ships market group, its class being the group of
+/// the type and its hull the market group below ships it is sold in.
+pub fn ships(sde: &path::Path) -> Result<Vec<universe::ShipType>> {
+    let market_groups: Vec<MarketGroup> = load(&sde.join("bsd/invMarketGroups.yaml"))?;
+    let parents = market_groups
+        .iter()
+        .map(|group| (group.id, group.parent))
+        .collect::<HashMap<_, _>>();
+    let hulls = market_groups
+        .iter()
+        .filter(|group| group.parent == Some(SHIPS_MARKET_GROUP))
+        .filter_map(|group| {
+            let name = group.name.as_ref().or(group.name_id.as_ref())?.english()?;
+            Some((group.id, singular(name)))
+        })
+        .collect::<HashMap<_, _>>();
+    let groups: HashMap<u64, Group> = load(&sde.join("fsd/groupIDs.yaml"))?;
+    let types: HashMap<u64, Type> = load(&sde.join("fsd/typeIDs.yaml"))?;
+
+    let mut ships = types
+        .values()
+        .filter_map(|item| {
+            let market_group = item.market_group?;
+            if root(&parents, market_group) != SHIPS_MARKET_GROUP {
+                return None;
+            }
+            let class = item
+                .group
+                .and_then(|group| groups.get(&group))
+                .and_then(|group| group.name.as_ref()?.english())
+                .cloned()
+                .unwrap_or_default();
+            Some(universe::ShipType {
+                name: item.name.as_ref()?.english()?.clone(),
+                class: class,
+                hull: hull(&parents, &hulls, market_group).unwrap_or_default(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    ships.sort_by(|a, b| a.name.cmp(&b.name));
+    ships.dedup_by(|a, b| a.name == b.name);
+    Ok(ships)
+}
+
+/// Name of the market group right below ships `group` belongs to.
+fn hull(
+    parents: &HashMap<u64, Option<u64>>,
+    hulls: &HashMap<u64, String>,
+    group: u64,
+) -> Option<String> {
+    let mut group = group;
+    for _ in 0..parents.len() {
+        if let Some(hull) = hulls.get(&group) {
+            return Some(hull.clone());
+        }
+        group = (*parents.get(&group)?)?;
+    }
+    None
+}
+
+/// `Frigate` for the `Frigates` market group.
+fn singular(name: &str) -> String {
+    if name.ends_with('s') {
+        name[..name.len() - 1].to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn root(parents: &HashMap<u64, Option<u64>>, group: u64) -> u64 {
+    let mut group = group;
+    // bounded, in case the export ever contains a cycle
+    for _ in 0..parents.len() {
+        match parents.get(&group) {
+            Some(&Some(parent)) => group = parent,
+            _ => break,
+        }
+    }
+    group
+}
+
+fn load<T: DeserializeOwned>(file: &path::Path) -> Result<T> {
+    let contents = fs::read(file).chain_err(|| format!("unable to read {}", file.display()))?;
+    serde_yaml::from_slice(&contents)
+        .chain_err(|| format!("invalid static data {}", file.display()))
+}
+
+fn dirs(dir: &path::Path) -> Result<Vec<path::PathBuf>> {
+    let mut dirs = dir
+        .read_dir()
+        .chain_err(|| format!("unable to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn name(dir: &path::Path) -> Result<String> {
+    dir.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.to_string())
+        .chain_err(|| format!("unexpected directory name {}", dir.display()))
+}
@@ -0,0 +1,129 @@
+use super::super::errors::*;
+use super::{System, Universe};
+
+use regex;
+use std::collections::BTreeMap;
+use toml;
+
+lazy_static! {
+    static ref SHORT_FORMS: Vec<(regex::Regex, &'static str)> = vec![
+        (r"(?i)^([a-z]).+-([a-z])", "$1-$2"),
+        (r"(?i)^([a-z])-([a-z])", "$1$2"),
+        (r"^(\w-\w)", "$1"),
+        (r"(\w-\w)$", "$1"),
+        (r"^(\w{2})\w+", "$1"),
+        (r"^(\w{3})-", "$1"),
+    ]
+    .into_iter()
+    .map(|(pattern, alias)| (regex::Regex::new(pattern).expect("must compile"), alias))
+    .collect();
+}
+
+/// Names a system can be found by: its upper cased name and, for names with
+/// a dash, the short forms used in intel channels, e.g. `1-A` or `1D` for
+/// `1DQ1-A`. Short forms keep the case of the name.
+pub fn aliases(name: &str) -> Vec<String> {
+    let mut aliases = vec![name.to_uppercase()];
+    if !name.contains("-") {
+        return aliases;
+    }
+
+    for &(ref pattern, template) in SHORT_FORMS.iter() {
+        if let Some(captures) = pattern.captures(name) {
+            let mut alias = String::new();
+            captures.expand(template, &mut alias);
+            if !aliases.contains(&alias) {
+                aliases.push(alias);
+            }
+        }
+    }
+    aliases
+}
+
+/// A shorthand read from an alias file.
+pub struct CustomAlias {
+    pub alias: String,
+    /// Region or constellation the shorthand is used in, everywhere if none.
+    pub scope: Option<String>,
+    pub id: String,
+}
+
+/// Reads an alias file, a TOML document mapping shorthands to system names.
+/// Shorthands in a table named after a region or constellation only apply
+/// to systems in it, and only to intel heard there:
+///
+/// ```toml
+/// 1DQ = "1DQ1-A"
+///
+/// [Delve]
+/// T5 = "T5ZI-S"
+/// ```
+pub fn parse(universe: &Universe, contents: &str) -> Result<Vec<CustomAlias>> {
+    let document: BTreeMap<String, toml::Value> = toml::from_str(contents)?;
+    let mut aliases = vec![];
+
+    for (key, value) in document.iter() {
+        match *value {
+            toml::Value::String(ref name) => {
+                aliases.push(custom(universe, key, name, None)?);
+            }
+            toml::Value::Table(ref table) => {
+                if !universe
+                    .systems
+                    .values()
+                    .any(|system| in_scope(system, key))
+                {
+                    bail!("no such region or constellation {}", key);
+                }
+                for (alias, value) in table.iter() {
+                    let name = value
+                        .as_str()
+                        .chain_err(|| format!("{}.{}: expected a system name", key, alias))?;
+                    aliases.push(custom(universe, alias, name, Some(key))?);
+                }
+            }
+            _ => bail!("{}: expected a system name or a table of aliases", key),
+        }
+    }
+    Ok(aliases)
+}
+
+/// Whether `system` lies in the region or constellation named `scope`,
+/// ignoring case and spaces as in `The Forge` for `TheForge`.
+pub fn in_scope(system: &System, scope: &str) -> bool {
+    let scope = compact(scope);
+    compact(&system.region) == scope || compact(&system.constelation) == scope
+}
+
+fn compact(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(|c| c.to_uppercase())
+        .collect()
+}
+
+fn custom(
+    universe: &Universe,
+    alias: &str,
+    name: &str,
+    scope: Option<&String>,
+) -> Result<CustomAlias> {
+    let system = universe
+        .resolve(name, None)
+        .into_iter()
+        .filter(|candidate| candidate.kind.is_certain())
+        .map(|candidate| candidate.system)
+        .find(|system| scope.map(|scope| in_scope(system, scope)).unwrap_or(true));
+
+    match system {
+        Some(system) => Ok(CustomAlias {
+            alias: alias.to_uppercase(),
+            scope: scope.cloned(),
+            id: system.id.clone(),
+        }),
+        None => match scope {
+            Some(scope) => bail!("{}: no such system {} in {}", alias, name, scope),
+            None => bail!("{}: no such system {}", alias, name),
+        },
+    }
+}
@@ -0,0 +1,89 @@
+use super::super::errors::*;
+use super::{System, Universe};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path;
+
+const SEPARATORS: [&'static str; 2] = ["<->", "»"];
+
+/// Jump bridge (Ansiblex) connections, usable in both directions.
+///
+/// The list file has one bridge per line, either as `FROM <-> TO` or as the
+/// in game structure name `FROM » TO - Name`. Empty lines and lines starting
+/// with `#` are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JumpBridges {
+    edges: HashMap<String, Vec<String>>,
+}
+
+impl JumpBridges {
+    pub fn load(universe: &Universe, path: &path::Path) -> Result<JumpBridges> {
+        let contents = fs::read_to_string(path)
+            .chain_err(|| format!("unable to read jump bridges from {}", path.display()))?;
+        JumpBridges::parse(universe, &contents)
+            .chain_err(|| format!("invalid jump bridge list {}", path.display()))
+    }
+
+    pub fn parse(universe: &Universe, contents: &str) -> Result<JumpBridges> {
+        let mut bridges = JumpBridges::default();
+
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("#") {
+                continue;
+            }
+
+            let (from, to) =
+                split(line).chain_err(|| format!("line {}: expected 'FROM <-> TO'", number + 1))?;
+            let from = universe
+                .find(from)
+                .chain_err(|| format!("line {}: no such system {}", number + 1, from))?;
+            let to = universe
+                .find(to)
+                .chain_err(|| format!("line {}: no such system {}", number + 1, to))?;
+
+            bridges.add(from, to);
+        }
+
+        Ok(bridges)
+    }
+
+    pub fn add(&mut self, from: &System, to: &System) {
+        self.edges
+            .entry(from.id.clone())
+            .or_insert_with(Vec::new)
+            .push(to.id.clone());
+        self.edges
+            .entry(to.id.clone())
+            .or_insert_with(Vec::new)
+            .push(from.id.clone());
+    }
+
+    pub fn len(&self) -> usize {
+        self.edges.values().map(|edges| edges.len()).sum::<usize>() / 2
+    }
+
+    pub fn neighbours(&self, id: &str) -> &[String] {
+        self.edges
+            .get(id)
+            .map(|edges| edges.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+fn split(line: &str) -> Option<(&str, &str)> {
+    let separator = SEPARATORS
+        .iter()
+        .find(|separator| line.contains(*separator))?;
+    let mut parts = line.splitn(2, separator);
+    let from = parts.next()?.trim();
+    // in game names carry the structure name after the destination
+    let to = parts.next()?.split(" - ").next()?.trim();
+
+    if from.is_empty() || to.is_empty() {
+        None
+    } else {
+        Some((from, to))
+    }
+}
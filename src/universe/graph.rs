@@ -0,0 +1,64 @@
+use super::System;
+
+use std::collections::HashMap;
+
+/// The stargate network with systems numbered `0..len()`. Neighbours live in
+/// a single adjacency array, `edges[offsets[i]..offsets[i + 1]]` being the
+/// systems one gate away from `i`, so a search only ever touches integers.
+#[derive(Debug, Default)]
+pub struct Graph {
+    ids: Vec<String>,
+    index: HashMap<String, u32>,
+    offsets: Vec<u32>,
+    edges: Vec<u32>,
+}
+
+impl Graph {
+    pub fn new(systems: &HashMap<String, System>) -> Graph {
+        let mut ids = systems.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+
+        let index = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.clone(), i as u32))
+            .collect::<HashMap<_, _>>();
+
+        let mut offsets = Vec::with_capacity(ids.len() + 1);
+        let mut edges = vec![];
+        offsets.push(0);
+        for id in ids.iter() {
+            edges.extend(
+                systems[id]
+                    .neighbours
+                    .iter()
+                    .filter_map(|neighbour| index.get(neighbour)),
+            );
+            offsets.push(edges.len() as u32);
+        }
+
+        Graph {
+            ids: ids,
+            index: index,
+            offsets: offsets,
+            edges: edges,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn index(&self, id: &str) -> Option<u32> {
+        self.index.get(id).cloned()
+    }
+
+    pub fn id(&self, index: u32) -> &str {
+        &self.ids[index as usize]
+    }
+
+    pub fn neighbours(&self, index: u32) -> &[u32] {
+        let index = index as usize;
+        &self.edges[self.offsets[index] as usize..self.offsets[index + 1] as usize]
+    }
+}
@@ -0,0 +1,147 @@
+use super::Universe;
+
+/// Problems listed in a load error, the rest are only counted.
+const REPORTED_PROBLEMS: usize = 20;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a over the systems, aliases and word lists in a fixed order, so the
+/// same contents always hash the same regardless of map ordering or the
+/// compiler version.
+pub fn checksum(universe: &Universe) -> String {
+    let mut hash = Fnv(FNV_OFFSET);
+
+    let mut ids = universe.systems.keys().collect::<Vec<_>>();
+    ids.sort();
+    for id in ids {
+        let system = &universe.systems[id];
+        hash.field(&system.id);
+        hash.field(&system.name);
+        hash.field(&system.constelation);
+        hash.field(&system.region);
+        if let Some(security) = system.security {
+            hash.field(&security.to_bits().to_string());
+        }
+        for neighbour in system.neighbours.iter() {
+            hash.field(neighbour);
+        }
+        hash.end();
+    }
+
+    let mut aliases = universe.system_aliases.keys().collect::<Vec<_>>();
+    aliases.sort();
+    for alias in aliases {
+        hash.field(alias);
+        for id in universe.system_aliases[alias].iter() {
+            hash.field(id);
+        }
+        hash.end();
+    }
+
+    for word in universe.ships.iter() {
+        hash.field(word);
+    }
+    hash.end();
+    // hashed only when present, so checksums of universes saved before
+    // ship types were added still match
+    let mut ship_types = universe.ship_types.keys().collect::<Vec<_>>();
+    ship_types.sort();
+    for word in ship_types {
+        let ship_type = &universe.ship_types[word];
+        hash.field(word);
+        hash.field(&ship_type.name);
+        hash.field(&ship_type.class);
+        hash.field(&ship_type.hull);
+        hash.end();
+    }
+    for word in universe.stop_words.iter() {
+        hash.field(word);
+    }
+    hash.end();
+
+    format!("{:016x}", hash.0)
+}
+
+struct Fnv(u64);
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn field(&mut self, value: &str) {
+        self.write(value.as_bytes());
+        self.write(&[0]);
+    }
+
+    fn end(&mut self) {
+        self.write(&[1]);
+    }
+}
+
+/// Everything that would make routing or name lookups silently wrong:
+/// systems stored under another id, stargates leading nowhere or only one
+/// way, and aliases of systems that do not exist.
+pub fn problems(universe: &Universe) -> Vec<String> {
+    let mut problems = vec![];
+    let describe = |id: &str| match universe.systems.get(id) {
+        Some(system) => format!("{} ({})", id, system.name),
+        None => id.to_string(),
+    };
+
+    let mut ids = universe.systems.keys().collect::<Vec<_>>();
+    ids.sort();
+    for id in ids {
+        let system = &universe.systems[id];
+        if &system.id != id {
+            problems.push(format!(
+                "system {} is stored as {}",
+                describe(&system.id),
+                id
+            ));
+        }
+        for neighbour in system.neighbours.iter() {
+            match universe.systems.get(neighbour) {
+                None => problems.push(format!(
+                    "system {}: stargate to unknown system {}",
+                    describe(id),
+                    neighbour
+                )),
+                Some(other) if !other.neighbours.contains(id) => problems.push(format!(
+                    "system {}: stargate to {} has no stargate back",
+                    describe(id),
+                    describe(neighbour)
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+
+    let mut aliases = universe.system_aliases.keys().collect::<Vec<_>>();
+    aliases.sort();
+    for alias in aliases {
+        for id in universe.system_aliases[alias].iter() {
+            if !universe.systems.contains_key(id) {
+                problems.push(format!("alias {}: unknown system {}", alias, id));
+            }
+        }
+    }
+    problems
+}
+
+/// One line per problem, up to `REPORTED_PROBLEMS` of them.
+pub fn report(problems: &[String]) -> String {
+    let mut lines = problems
+        .iter()
+        .take(REPORTED_PROBLEMS)
+        .map(|problem| format!("  {}", problem))
+        .collect::<Vec<_>>();
+    if problems.len() > REPORTED_PROBLEMS {
+        lines.push(format!("  and {} more", problems.len() - REPORTED_PROBLEMS));
+    }
+    lines.join("\n")
+}
@@ -1,177 +1,423 @@
+use super::errors::*;
+
 use std::collections::HashMap;
+use std::fs;
 use std::fs::File;
+use std::io;
+use std::path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 extern crate bson;
-use self::bson::{decode_document, Bson};
+use self::bson::{decode_document, encode_document, Bson};
 extern crate serde;
 
-lazy_static! {
-    static ref UNIVERSE: Universe = {
-        match init() {
-            Some(universe) => universe,
-            None => panic!("unable to load universe"),
-        }
-    };
-}
+mod aliases;
+mod bridges;
+mod graph;
+mod integrity;
+mod resolver;
+mod routing;
+mod ships;
+mod wormholes;
+use self::aliases::aliases;
+pub use self::bridges::JumpBridges;
+use self::graph::Graph;
+pub use self::resolver::{Candidate, Vicinity};
+pub use self::routing::{distance, route, Avoidance, Hop, Preference, RouteOptions};
+pub use self::ships::ShipType;
+pub use self::wormholes::Wormholes;
+
+static NEXT_UNIVERSE: AtomicUsize = AtomicUsize::new(0);
+
+/// Version of the universe file layout written by `Universe::save`. Files
+/// without one predate versioning and are read as version 0.
+const SCHEMA_VERSION: i32 = 1;
 
-fn init() -> Option<Universe> {
-    let mut file = File::open("universe.bson").ok()?;
-    let doc = decode_document(&mut file).ok()?;
-    match bson::from_bson(Bson::Document(doc)) {
-        Ok(universe) => {
-            return Some(universe);
+#[cfg(feature = "embedded-universe")]
+const EMBEDDED_UNIVERSE: &'static [u8] = include_bytes!("../../universe.bson");
+#[cfg(feature = "embedded-universe")]
+const EMBEDDED_SHIPS: &'static str = include_str!("../../data/ships.txt");
+#[cfg(feature = "embedded-universe")]
+const EMBEDDED_SHIP_CLASSES: &'static str = include_str!("../../data/ship_classes.txt");
+#[cfg(feature = "embedded-universe")]
+const EMBEDDED_STOP_WORDS: &'static str = include_str!("../../data/stop_words.txt");
+
+#[cfg(not(feature = "embedded-universe"))]
+const DEFAULT_UNIVERSE: &'static str = "universe.bson";
+
+/// Loads `file` when given, the built in universe otherwise.
+pub fn load(file: Option<path::PathBuf>) -> Result<Universe> {
+    match file {
+        Some(file) => {
+            let universe = Universe::load(&file)?;
+            info!(
+                "Loaded universe from {} ({})",
+                file.display(),
+                universe.describe()
+            );
+            Ok(universe)
         }
-        Err(e) => {
-            println!("unable to load 'universe.bson'. Reason: {}", e);
-            return None;
+        None => {
+            let universe = Universe::builtin()?;
+            debug!("Loaded the built in universe ({})", universe.describe());
+            Ok(universe)
         }
-    };
+    }
 }
 
-#[derive(Clone, Hash, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct System {
     pub id: String,
     pub name: String,
     pub constelation: String,
     pub region: String,
     pub neighbours: Vec<String>,
+    /// Unknown in universes imported before it was recorded.
+    #[serde(default)]
+    pub security: Option<f64>,
 }
 
-impl System {
-    pub fn find(name: &str) -> Option<System> {
-        UNIVERSE
-            .system_aliases
-            .get(&name.to_string().to_uppercase())?
-            .iter()
-            .find(|&name| UNIVERSE.systems.contains_key(name))
-            .map(|name| UNIVERSE.systems.get(name).unwrap().clone())
-    }
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SecurityClass {
+    High,
+    Low,
+    Null,
+}
 
-    pub fn get(id: &str) -> System {
-        UNIVERSE.systems.get(id).unwrap().clone()
+impl System {
+    /// Security class as shown in game, where the status is rounded to one
+    /// decimal, if the status is known.
+    pub fn security_class(&self) -> Option<SecurityClass> {
+        let security = self.security?;
+        Some(if security >= 0.45 {
+            SecurityClass::High
+        } else if security > 0.0 {
+            SecurityClass::Low
+        } else {
+            SecurityClass::Null
+        })
     }
 }
 
+/// Systems, their stargates and the words used to tell system names apart
+/// from the rest of an intel line.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Universe {
-    pub systems: HashMap<String, System>,
-    pub system_aliases: HashMap<String, Vec<String>>,
-    pub ships: Vec<String>,
-    pub stop_words: Vec<String>,
+    #[serde(default)]
+    schema: i32,
+    /// Build of the static data export the universe was imported from.
+    #[serde(default)]
+    sde_build: Option<i64>,
+    #[serde(default)]
+    checksum: Option<String>,
+    systems: HashMap<String, System>,
+    system_aliases: HashMap<String, Vec<String>>,
+    ships: Vec<String>,
+    /// Kinds of ships by upper cased ship name or shorthand.
+    #[serde(default)]
+    ship_types: HashMap<String, ShipType>,
+    stop_words: Vec<String>,
+    #[serde(skip)]
+    id: usize,
+    #[serde(skip)]
+    graph: Graph,
+    /// Shorthands read from alias files, with the region or constellation
+    /// they apply in, if any, and the id of the system.
+    #[serde(skip)]
+    custom_aliases: HashMap<String, Vec<(Option<String>, String)>>,
+    /// Upper cased system names and ids, sorted by name.
+    #[serde(skip)]
+    names: Vec<(String, String)>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Route {
-    pub systems: Vec<System>,
-    pub distance: u16,
-    pub destination: System,
-    pub source: System,
-}
+impl Universe {
+    pub fn load(path: &path::Path) -> Result<Universe> {
+        let mut file =
+            File::open(path).chain_err(|| format!("unable to open universe {}", path.display()))?;
+        Universe::read(&mut file)
+            .chain_err(|| format!("unable to load universe {}", path.display()))
+    }
 
-#[inline]
-fn unwrap_node<T: Clone>(node: Option<&Option<T>>) -> Option<T> {
-    Some(node.cloned()??)
-}
+    /// The universe compiled into the binary, with the ship names, ship
+    /// shorthands and stop words of `data/` added to those of the universe
+    /// file.
+    #[cfg(feature = "embedded-universe")]
+    pub fn builtin() -> Result<Universe> {
+        let mut universe = Universe::read(&mut io::Cursor::new(EMBEDDED_UNIVERSE))
+            .chain_err(|| "unable to load the embedded universe")?;
 
-#[inline]
-pub fn ship_exists(name: &str) -> bool {
-    UNIVERSE.ships.contains(&name.to_uppercase())
-}
+        universe.ships.extend(words(EMBEDDED_SHIPS));
+        universe.ships.sort();
+        universe.ships.dedup();
+        universe.add_ship_types(ShipType::parse(EMBEDDED_SHIP_CLASSES)?);
+        universe.stop_words.extend(words(EMBEDDED_STOP_WORDS));
+        universe.stop_words.sort();
+        universe.stop_words.dedup();
+        Ok(universe)
+    }
 
-#[inline]
-pub fn is_stop_word(word: &str) -> bool {
-    UNIVERSE.stop_words.contains(&word.to_uppercase())
-}
+    /// `universe.bson` in the working directory, for builds without an
+    /// embedded universe.
+    #[cfg(not(feature = "embedded-universe"))]
+    pub fn builtin() -> Result<Universe> {
+        Universe::load(path::Path::new(DEFAULT_UNIVERSE))
+    }
 
-pub fn route(source: &System, destination: &System) -> Option<Route> {
-    if source.neighbours.len() == 0 || destination.neighbours.len() == 0 {
-        return None;
+    /// Reads a BSON encoded universe.
+    pub fn read<R: io::Read>(reader: &mut R) -> Result<Universe> {
+        let doc = decode_document(reader).chain_err(|| "invalid universe data")?;
+        let universe: Universe =
+            bson::from_bson(Bson::Document(doc)).chain_err(|| "unexpected universe layout")?;
+        universe.verify()?;
+        Ok(universe.indexed())
     }
-    let (pred, succ, w) = walk(&source.id, &destination.id);
-    let mut path: Vec<String> = vec![];
-    let mut node = Some(w);
 
-    while node.is_some() {
-        let value = node.take().unwrap();
-        node = unwrap_node(pred.get(&value));
-        path.push(value);
+    /// Refuses universes written by a newer version, altered since they were
+    /// saved or with systems, stargates and aliases that do not add up.
+    fn verify(&self) -> Result<()> {
+        if self.schema > SCHEMA_VERSION {
+            bail!(
+                "universe format version {} is newer than the supported version {}",
+                self.schema,
+                SCHEMA_VERSION
+            );
+        }
+        if let Some(ref expected) = self.checksum {
+            let actual = integrity::checksum(self);
+            if &actual != expected {
+                bail!(
+                    "universe checksum mismatch, the file says {} but its contents hash to {}",
+                    expected,
+                    actual
+                );
+            }
+        }
+
+        let problems = integrity::problems(self);
+        if !problems.is_empty() {
+            bail!(
+                "{} problems in universe data:\n{}",
+                problems.len(),
+                integrity::report(&problems)
+            );
+        }
+
+        let unknown = self.unknown_security();
+        if unknown > 0 {
+            warn!(
+                "the universe has no security status for {} of {} systems, safer and less \
+                 secure routes take the shortest way through them until it is imported again \
+                 with `eintel import`",
+                unknown,
+                self.len()
+            );
+        }
+        Ok(())
     }
 
-    path.reverse();
+    /// Builds a universe from its systems, each of them found by its name
+    /// and the short forms of it. Of ship types given for the same word, the
+    /// first one is kept.
+    pub fn new(
+        systems: Vec<System>,
+        ships: Vec<String>,
+        ship_types: Vec<(String, ShipType)>,
+        stop_words: Vec<String>,
+        sde_build: Option<i64>,
+    ) -> Universe {
+        let mut system_aliases: HashMap<String, Vec<String>> = HashMap::new();
+        for system in systems.iter() {
+            for alias in aliases(&system.name) {
+                system_aliases
+                    .entry(alias)
+                    .or_insert_with(Vec::new)
+                    .push(system.id.clone());
+            }
+        }
+
+        let mut universe = Universe {
+            schema: SCHEMA_VERSION,
+            sde_build: sde_build,
+            checksum: None,
+            systems: systems
+                .into_iter()
+                .map(|system| (system.id.clone(), system))
+                .collect(),
+            system_aliases: system_aliases,
+            ships: ships,
+            ship_types: HashMap::new(),
+            stop_words: stop_words,
+            id: 0,
+            graph: Graph::default(),
+            custom_aliases: HashMap::new(),
+            names: vec![],
+        };
+        universe.add_ship_types(ship_types);
+        universe.indexed()
+    }
 
-    node = Some(path.last().unwrap().to_string());
-    while node.is_some() {
-        let value = node.take().unwrap();
-        node = unwrap_node(succ.get(&value));
-        path.push(value);
+    fn indexed(mut self) -> Universe {
+        self.id = NEXT_UNIVERSE.fetch_add(1, Ordering::Relaxed);
+        self.graph = Graph::new(&self.systems);
+        self.names = self
+            .systems
+            .values()
+            .map(|system| (system.name.to_uppercase(), system.id.clone()))
+            .collect();
+        self.names.sort();
+        self
     }
 
-    let mut system_path = path
-        .iter()
-        .map(|id| System::get(id).clone())
-        .collect::<Vec<_>>();
+    /// Adds the shorthands of an alias file, see `aliases::parse`.
+    pub fn add_aliases(&mut self, path: &path::Path) -> Result<usize> {
+        let contents = fs::read_to_string(path)
+            .chain_err(|| format!("unable to read aliases from {}", path.display()))?;
+        let custom = aliases::parse(self, &contents)
+            .chain_err(|| format!("invalid alias file {}", path.display()))?;
 
-    system_path.dedup();
-    let len = system_path.len() as u16;
-    if len > 0 {
-        Some(Route {
-            systems: system_path,
-            distance: len - 1,
-            destination: destination.clone(),
-            source: source.clone(),
-        })
-    } else {
-        None
+        let count = custom.len();
+        for alias in custom.into_iter() {
+            self.custom_aliases
+                .entry(alias.alias)
+                .or_insert_with(Vec::new)
+                .push((alias.scope, alias.id));
+        }
+        Ok(count)
     }
-}
 
-type GraphPath = HashMap<String, Option<String>>;
-
-fn walk(source: &str, destination: &str) -> (GraphPath, GraphPath, String) {
-    let mut forward = vec![source.to_string()];
-    let mut backward = vec![destination.to_string()];
-    let mut pred: GraphPath = HashMap::new();
-    let mut succ: GraphPath = HashMap::new();
-
-    pred.insert(source.to_string(), None);
-    succ.insert(destination.to_string(), None);
-
-    if source == destination {
-        return (pred, succ, source.to_string());
-    }
-
-    while forward.len() > 0 && backward.len() > 0 {
-        if forward.len() <= backward.len() {
-            let current = forward;
-            forward = vec![];
-            for v in current.iter() {
-                for w in System::get(v).neighbours.iter() {
-                    if pred.get(w).is_none() {
-                        forward.push(w.to_string());
-                        pred.insert(w.to_string(), Some(v.to_string()));
-                    }
-                    if succ.contains_key(w) {
-                        return (pred, succ, w.to_string());
-                    }
-                }
-            }
-        } else {
-            let current = backward;
-            backward = vec![];
-
-            for v in current.iter() {
-                for w in System::get(v).neighbours.iter() {
-                    if succ.get(w).is_none() {
-                        backward.push(w.to_string());
-                        succ.insert(w.to_string(), Some(v.to_string()));
-                    }
-                    if pred.contains_key(w) {
-                        return (pred, succ, w.clone());
-                    }
-                }
-            }
+    /// Writes the universe in the format `load` reads, along with the
+    /// checksum of its contents.
+    pub fn save(&self, path: &path::Path) -> Result<()> {
+        let mut doc = match bson::to_bson(self).chain_err(|| "unable to encode universe")? {
+            Bson::Document(doc) => doc,
+            _ => bail!("universe is not encoded as a document"),
+        };
+        doc.insert("checksum", integrity::checksum(self));
+        let mut file = File::create(path)
+            .chain_err(|| format!("unable to write universe to {}", path.display()))?;
+        encode_document(&mut file, &doc)
+            .chain_err(|| format!("unable to write universe to {}", path.display()))
+    }
+
+    /// Size and origin of the universe, for logs.
+    pub fn describe(&self) -> String {
+        let build = match self.sde_build {
+            Some(build) => format!("SDE build {}", build),
+            None => "unknown SDE build".to_string(),
+        };
+        format!(
+            "{} systems, format version {}, {}",
+            self.len(),
+            self.schema,
+            build
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    /// Number of systems whose security status is not known.
+    pub fn unknown_security(&self) -> usize {
+        self.systems
+            .values()
+            .filter(|system| system.security.is_none())
+            .count()
+    }
+
+    /// The system called `name` or, failing that, known by it as a short
+    /// form.
+    pub fn find(&self, name: &str) -> Option<&System> {
+        self.resolve(name, None)
+            .into_iter()
+            .find(|candidate| candidate.kind.is_certain())
+            .map(|candidate| candidate.system)
+    }
+
+    /// Every system `token` may refer to, most likely first. Names and their
+    /// short forms are preferred, prefixes and typos are only considered
+    /// when nothing is called `token`.
+    pub fn resolve(&self, token: &str, vicinity: Option<&Vicinity>) -> Vec<Candidate<'_>> {
+        resolver::resolve(self, token, vicinity)
+    }
+
+    pub fn systems(&self) -> Vec<&System> {
+        self.systems.values().collect()
+    }
+
+    pub fn ship_exists(&self, name: &str) -> bool {
+        let name = name.to_uppercase();
+        self.ships.contains(&name) || self.ship_types.contains_key(&name)
+    }
+
+    /// The kind of ship a ship name or shorthand stands for. Ships only
+    /// known by name are of no particular class.
+    pub fn ship(&self, name: &str) -> Option<ShipType> {
+        let name = name.to_uppercase();
+        if let Some(ship_type) = self.ship_types.get(&name) {
+            return Some(ship_type.clone());
+        }
+        if !self.ships.contains(&name) {
+            return None;
         }
+        Some(ShipType::named(&name))
     }
 
-    return (HashMap::default(), HashMap::default(), String::default());
+    /// Adds kinds of ships for the words not known yet.
+    fn add_ship_types(&mut self, ship_types: Vec<(String, ShipType)>) {
+        for (word, ship_type) in ship_types.into_iter() {
+            self.ship_types.entry(word).or_insert(ship_type);
+        }
+    }
+
+    pub fn is_stop_word(&self, word: &str) -> bool {
+        self.stop_words.contains(&word.to_uppercase())
+    }
+}
+
+#[cfg(feature = "embedded-universe")]
+fn words(list: &str) -> Vec<String> {
+    list.lines()
+        .map(|word| word.trim().to_uppercase())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Small universes for tests.
+#[cfg(test)]
+pub mod testing {
+    use super::{System, Universe};
+
+    /// A universe of one region with the `systems` named, linked by the
+    /// stargates between each pair of `gates`, and the `stop_words` given.
+    pub fn universe(systems: &[&str], gates: &[(&str, &str)], stop_words: &[&str]) -> Universe {
+        let id = |name: &str| {
+            let index = systems
+                .iter()
+                .position(|system| *system == name)
+                .expect("gate of an unknown system");
+            (30_000_001 + index).to_string()
+        };
+        let systems = systems
+            .iter()
+            .map(|&name| System {
+                id: id(name),
+                name: name.to_string(),
+                constelation: "Constellation".to_string(),
+                region: "Region".to_string(),
+                neighbours: gates
+                    .iter()
+                    .filter_map(|&(from, to)| match name {
+                        _ if name == from => Some(id(to)),
+                        _ if name == to => Some(id(from)),
+                        _ => None,
+                    })
+                    .collect(),
+                security: None,
+            })
+            .collect();
+        let stop_words = stop_words.iter().map(|word| word.to_string()).collect();
+
+        Universe::new(systems, vec![], vec![], stop_words, None)
+    }
 }
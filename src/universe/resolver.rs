@@ -0,0 +1,225 @@
+use super::aliases::in_scope;
+use super::{distance, RouteOptions, System, Universe};
+use std::cmp::Ordering;
+use strsim;
+
+/// Shorter tokens are only looked up by name and alias, too many systems
+/// start with or are a typo away from two letters.
+const MIN_PREFIX_LENGTH: usize = 3;
+const MIN_TYPO_LENGTH: usize = 4;
+/// Names up to this long may have one typo, longer ones two.
+const SHORT_NAME_LENGTH: usize = 6;
+
+/// How a token matched a system, best first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Match {
+    /// The name of the system, in any case.
+    Exact,
+    /// A shorthand of an alias file.
+    Custom,
+    /// One of the short forms of the name, e.g. `1-A` for `1DQ1-A`.
+    Alias,
+    /// The beginning of the name, e.g. `1DQ` for `1DQ1-A`.
+    Prefix,
+    /// The name with a few letters mistyped, e.g. `JTIA` for `Jita`.
+    Typo(usize),
+}
+
+impl Match {
+    /// Whether the token names the system rather than resembling its name.
+    pub fn is_certain(&self) -> bool {
+        *self <= Match::Alias
+    }
+}
+
+/// A system a token may refer to.
+#[derive(Clone, Debug)]
+pub struct Candidate<'a> {
+    pub system: &'a System,
+    pub kind: Match,
+    /// How likely the token refers to the system, between 0 and 1.
+    pub confidence: f64,
+    /// Jumps from the origin of the vicinity the candidates were restricted
+    /// to, if any.
+    pub distance: Option<u16>,
+}
+
+/// Where the listener is. Systems in the listener's region or within
+/// `reach` jumps are preferred over those farther away, and tokens that only
+/// resemble a system name are only taken for systems that close.
+pub struct Vicinity<'a> {
+    pub origin: &'a System,
+    pub options: &'a RouteOptions,
+    pub reach: u16,
+}
+
+impl<'a> Vicinity<'a> {
+    fn contains(&self, candidate: &Candidate) -> bool {
+        candidate.system.region == self.origin.region
+            || candidate
+                .distance
+                .map(|jumps| jumps <= self.reach)
+                .unwrap_or(false)
+    }
+}
+
+/// Every system `token` may refer to, most likely first.
+pub fn resolve<'a>(
+    universe: &'a Universe,
+    token: &str,
+    vicinity: Option<&Vicinity>,
+) -> Vec<Candidate<'a>> {
+    let token = token.trim().to_uppercase();
+    if token.is_empty() {
+        return vec![];
+    }
+
+    let mut candidates = named(universe, &token, vicinity);
+    if candidates.is_empty() {
+        candidates = resembling(universe, &token);
+    }
+
+    if let Some(vicinity) = vicinity {
+        for candidate in candidates.iter_mut() {
+            candidate.distance = distance(
+                universe,
+                vicinity.origin,
+                candidate.system,
+                vicinity.options,
+            );
+        }
+        let nearby = candidates
+            .iter()
+            .any(|candidate| vicinity.contains(candidate));
+        candidates.retain(|candidate| {
+            vicinity.contains(candidate) || (!nearby && candidate.kind.is_certain())
+        });
+    }
+
+    candidates.sort_by(|a, b| {
+        a.kind
+            .cmp(&b.kind)
+            .then_with(|| match (a.distance, b.distance) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            })
+            .then_with(|| a.system.name.cmp(&b.system.name))
+    });
+    candidates
+}
+
+/// Systems called `token` or known by it. Shorthands of alias files scoped
+/// to a region or constellation only count when the listener is there.
+fn named<'a>(
+    universe: &'a Universe,
+    token: &str,
+    vicinity: Option<&Vicinity>,
+) -> Vec<Candidate<'a>> {
+    let custom = universe
+        .custom_aliases
+        .get(token)
+        .into_iter()
+        .flatten()
+        .filter(|&&(ref scope, _)| match (scope, vicinity) {
+            (&Some(ref scope), Some(vicinity)) => in_scope(vicinity.origin, scope),
+            _ => true,
+        })
+        .map(|&(_, ref id)| (Match::Custom, id));
+    let generated = universe
+        .system_aliases
+        .get(token)
+        .into_iter()
+        .flatten()
+        .map(|id| (Match::Alias, id));
+
+    let mut candidates: Vec<Candidate> = vec![];
+    for (kind, id) in custom.chain(generated) {
+        let system = match universe.systems.get(id) {
+            Some(system) => system,
+            None => continue,
+        };
+        if candidates
+            .iter()
+            .any(|candidate| candidate.system.id == system.id)
+        {
+            continue;
+        }
+        let kind = if system.name.to_uppercase() == token {
+            Match::Exact
+        } else {
+            kind
+        };
+        candidates.push(candidate(system, kind, token));
+    }
+    candidates
+}
+
+/// Systems whose name starts with `token` or is a few typos away from it.
+/// Numbers and counts like `+5` or `x15` never resemble a name, though they
+/// may be the short form of one.
+fn resembling<'a>(universe: &'a Universe, token: &str) -> Vec<Candidate<'a>> {
+    let length = token.chars().count();
+    let mut candidates = vec![];
+    if is_number(token) {
+        return candidates;
+    }
+
+    if length >= MIN_PREFIX_LENGTH {
+        let start = match universe
+            .names
+            .binary_search_by(|&(ref name, _)| name.as_str().cmp(token))
+        {
+            Ok(index) | Err(index) => index,
+        };
+        for &(ref name, ref id) in universe.names[start..].iter() {
+            if !name.starts_with(token) {
+                break;
+            }
+            if let Some(system) = universe.systems.get(id) {
+                candidates.push(candidate(system, Match::Prefix, token));
+            }
+        }
+    }
+
+    if length >= MIN_TYPO_LENGTH {
+        let allowed = if length <= SHORT_NAME_LENGTH { 1 } else { 2 };
+        for &(ref name, ref id) in universe.names.iter() {
+            let size = name.chars().count();
+            if size.max(length) - size.min(length) > allowed || name.starts_with(token) {
+                continue;
+            }
+            let typos = strsim::damerau_levenshtein(name, token);
+            if typos > allowed {
+                continue;
+            }
+            if let Some(system) = universe.systems.get(id) {
+                candidates.push(candidate(system, Match::Typo(typos), token));
+            }
+        }
+    }
+    candidates
+}
+
+fn is_number(token: &str) -> bool {
+    let digits = token.trim_matches(|c| c == '+' || c == 'X');
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+fn candidate<'a>(system: &'a System, kind: Match, token: &str) -> Candidate<'a> {
+    let name = system.name.chars().count().max(1) as f64;
+    let confidence = match kind {
+        Match::Exact => 1.0,
+        Match::Custom => 0.95,
+        Match::Alias => 0.9,
+        Match::Prefix => 0.5 + 0.3 * (token.chars().count() as f64 / name).min(1.0),
+        Match::Typo(typos) => (0.6 - 0.2 * typos as f64).max(0.1),
+    };
+    Candidate {
+        system: system,
+        kind: kind,
+        confidence: confidence,
+        distance: None,
+    }
+}
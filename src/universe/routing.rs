@@ -0,0 +1,485 @@
+use super::bridges::JumpBridges;
+use super::graph::Graph;
+use super::wormholes::Wormholes;
+use super::{SecurityClass, System, Universe};
+
+use chrono::prelude::*;
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::fmt;
+use std::sync::Mutex;
+
+/// Extra cost of a jump into a system the preference steers away from, large
+/// enough that any detour through preferred systems is taken first.
+const PENALTY: u32 = 10_000;
+
+/// Once distance tables for this many systems are kept the cache starts over.
+const DISTANCE_TABLES: usize = 64;
+
+/// Marks a system that cannot be reached in a distance table.
+const UNREACHABLE: u16 = u16::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hop {
+    Stargate,
+    JumpBridge,
+    Wormhole,
+}
+
+/// A route through the systems of a universe, borrowed from it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route<'a> {
+    pub systems: Vec<&'a System>,
+    /// How each jump is made, `hops[i]` leads from `systems[i]` to
+    /// `systems[i + 1]`.
+    pub hops: Vec<Hop>,
+    pub distance: u16,
+    pub destination: &'a System,
+    pub source: &'a System,
+}
+
+/// Systems, constellations and regions a route must not pass through.
+/// Names are compared ignoring case and spaces, so both "The Forge" and
+/// "TheForge" match.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Avoidance {
+    #[serde(default)]
+    pub systems: Vec<String>,
+    #[serde(default)]
+    pub constellations: Vec<String>,
+    #[serde(default)]
+    pub regions: Vec<String>,
+}
+
+impl Avoidance {
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty() && self.constellations.is_empty() && self.regions.is_empty()
+    }
+
+    pub fn avoids(&self, system: &System) -> bool {
+        contains(&self.systems, &system.name)
+            || contains(&self.constellations, &system.constelation)
+            || contains(&self.regions, &system.region)
+    }
+}
+
+/// Mirrors the in game autopilot route preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preference {
+    Shortest,
+    Safer,
+    LessSecure,
+}
+
+impl Default for Preference {
+    fn default() -> Preference {
+        Preference::Shortest
+    }
+}
+
+impl Preference {
+    /// Systems whose security status is not known are neither preferred nor
+    /// avoided.
+    fn cost(&self, system: &System) -> u32 {
+        match (*self, system.security_class()) {
+            (Preference::Safer, Some(SecurityClass::High)) => 1,
+            (Preference::Safer, Some(_)) => PENALTY,
+            (Preference::LessSecure, Some(SecurityClass::High)) => PENALTY,
+            _ => 1,
+        }
+    }
+}
+
+thread_local! {
+    static SEARCH: RefCell<Search> = RefCell::new(Search::default());
+}
+
+/// Routing constraints and the extra connections on top of the stargates.
+///
+/// They are compiled into a `Network` for the universe they are first routed
+/// in, changes made after that are not picked up. A clone starts uncompiled.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteOptions {
+    #[serde(default)]
+    pub avoid: Avoidance,
+    #[serde(default)]
+    pub preference: Preference,
+    #[serde(skip)]
+    pub bridges: JumpBridges,
+    #[serde(skip)]
+    pub wormholes: Wormholes,
+    #[serde(skip)]
+    network: NetworkCache,
+}
+
+impl RouteOptions {
+    /// Runs `f` on the network compiled for `universe`, compiling it first
+    /// when it is missing or a wormhole it was built with has collapsed.
+    fn with_network<T, F: FnOnce(&mut Network) -> T>(&self, universe: &Universe, f: F) -> T {
+        let mut network = match self.network.0.lock() {
+            Ok(network) => network,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if network
+            .as_ref()
+            .map(|network| network.expired(universe))
+            .unwrap_or(true)
+        {
+            *network = Some(Network::new(universe, self));
+        }
+        f(network.as_mut().expect("just compiled"))
+    }
+}
+
+#[derive(Default)]
+struct NetworkCache(Mutex<Option<Network>>);
+
+impl Clone for NetworkCache {
+    fn clone(&self) -> NetworkCache {
+        NetworkCache::default()
+    }
+}
+
+impl PartialEq for NetworkCache {
+    fn eq(&self, _: &NetworkCache) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for NetworkCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("NetworkCache")
+    }
+}
+
+/// `RouteOptions` compiled against the universe graph: stargates, jump
+/// bridges and live wormholes in one adjacency array indexed like the graph,
+/// the cost of entering each system and the distances from every system
+/// routed from so far.
+struct Network {
+    universe: usize,
+    preference: Preference,
+    offsets: Vec<u32>,
+    edges: Vec<(u32, Hop)>,
+    costs: Vec<u32>,
+    avoided: Vec<bool>,
+    expires_at: Option<DateTime<Utc>>,
+    distances: HashMap<u32, Vec<u16>>,
+}
+
+impl Network {
+    fn new(universe: &Universe, options: &RouteOptions) -> Network {
+        let graph = &universe.graph;
+        let mut offsets = Vec::with_capacity(graph.len() + 1);
+        let mut edges = vec![];
+        let mut costs = Vec::with_capacity(graph.len());
+        let mut avoided = Vec::with_capacity(graph.len());
+
+        offsets.push(0);
+        for index in 0..graph.len() as u32 {
+            let id = graph.id(index);
+            let start = edges.len();
+
+            edges.extend(
+                graph
+                    .neighbours(index)
+                    .iter()
+                    .map(|&neighbour| (neighbour, Hop::Stargate)),
+            );
+            for neighbour in options.bridges.neighbours(id).iter() {
+                link(&mut edges, graph, start, neighbour, Hop::JumpBridge);
+            }
+            for neighbour in options.wormholes.neighbours(id).iter() {
+                link(&mut edges, graph, start, neighbour, Hop::Wormhole);
+            }
+            offsets.push(edges.len() as u32);
+
+            let system = &universe.systems[id];
+            costs.push(options.preference.cost(system));
+            avoided.push(!options.avoid.is_empty() && options.avoid.avoids(system));
+        }
+
+        Network {
+            universe: universe.id,
+            preference: options.preference,
+            offsets: offsets,
+            edges: edges,
+            costs: costs,
+            avoided: avoided,
+            expires_at: options.wormholes.next_expiry(),
+            distances: HashMap::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.costs.len()
+    }
+
+    /// Whether the network no longer matches `universe` or a wormhole has
+    /// collapsed since it was compiled.
+    fn expired(&self, universe: &Universe) -> bool {
+        self.universe != universe.id
+            || self
+                .expires_at
+                .map(|expires_at| expires_at <= Utc::now())
+                .unwrap_or(false)
+    }
+
+    fn neighbours(&self, index: u32) -> &[(u32, Hop)] {
+        let index = index as usize;
+        &self.edges[self.offsets[index] as usize..self.offsets[index + 1] as usize]
+    }
+
+    fn hop(&self, from: u32, to: u32) -> Hop {
+        self.neighbours(from)
+            .iter()
+            .find(|&&(neighbour, _)| neighbour == to)
+            .map(|&(_, hop)| hop)
+            .unwrap_or(Hop::Stargate)
+    }
+
+    /// Avoided systems can still be the end points of a route, they are
+    /// reached but never left.
+    fn expandable(&self, index: u32, source: u32) -> bool {
+        index == source || !self.avoided[index as usize]
+    }
+
+    /// Searches for `destination`, or every system when there is none.
+    /// Systems without any connection are unreachable even from themselves.
+    fn search(&self, search: &mut Search, source: u32, destination: Option<u32>) -> bool {
+        let isolated = |index| self.neighbours(index).is_empty();
+        if isolated(source) || destination.map(isolated).unwrap_or(false) {
+            return false;
+        }
+        search.run(self, source, destination)
+    }
+
+    /// Looks `destination` up in the distance table of `source`, building
+    /// the table with a single search when it is the first one from there.
+    fn distance(&mut self, source: u32, destination: u32) -> Option<u16> {
+        if !self.distances.contains_key(&source) {
+            if self.distances.len() >= DISTANCE_TABLES {
+                self.distances.clear();
+            }
+            let table = SEARCH.with(|search| {
+                let mut search = search.borrow_mut();
+                if self.search(&mut search, source, None) {
+                    search.distances()
+                } else {
+                    vec![UNREACHABLE; self.len()]
+                }
+            });
+            self.distances.insert(source, table);
+        }
+
+        match self.distances[&source][destination as usize] {
+            UNREACHABLE => None,
+            distance => Some(distance),
+        }
+    }
+}
+
+fn link(edges: &mut Vec<(u32, Hop)>, graph: &Graph, start: usize, id: &str, hop: Hop) {
+    if let Some(index) = graph.index(id) {
+        if !edges[start..].iter().any(|&(known, _)| known == index) {
+            edges.push((index, hop));
+        }
+    }
+}
+
+/// Scratch space shared by the searches on a thread. A system counts as
+/// reached only when marked with the current generation, so nothing has to
+/// be cleared or allocated between searches.
+#[derive(Default)]
+struct Search {
+    generation: u32,
+    reached: Vec<u32>,
+    costs: Vec<u32>,
+    jumps: Vec<u16>,
+    pred: Vec<u32>,
+    queue: VecDeque<u32>,
+    heap: BinaryHeap<Reverse<(u32, u32)>>,
+}
+
+impl Search {
+    fn reset(&mut self, len: usize) {
+        if self.reached.len() != len {
+            self.reached = vec![0; len];
+            self.costs = vec![0; len];
+            self.jumps = vec![0; len];
+            self.pred = vec![0; len];
+            self.generation = 0;
+        }
+
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            for reached in self.reached.iter_mut() {
+                *reached = 0;
+            }
+            self.generation = 1;
+        }
+        self.queue.clear();
+        self.heap.clear();
+    }
+
+    fn reached(&self, index: u32) -> bool {
+        self.reached[index as usize] == self.generation
+    }
+
+    fn reach(&mut self, index: u32, pred: u32, cost: u32) {
+        let jumps = if index == pred {
+            0
+        } else {
+            self.jumps[pred as usize] + 1
+        };
+        let index = index as usize;
+        self.reached[index] = self.generation;
+        self.costs[index] = cost;
+        self.jumps[index] = jumps;
+        self.pred[index] = pred;
+    }
+
+    fn run(&mut self, network: &Network, source: u32, destination: Option<u32>) -> bool {
+        self.reset(network.len());
+        self.reach(source, source, 0);
+
+        match network.preference {
+            Preference::Shortest => self.breadth_first(network, source, destination),
+            _ => self.cheapest_first(network, source, destination),
+        }
+    }
+
+    fn breadth_first(&mut self, network: &Network, source: u32, destination: Option<u32>) -> bool {
+        self.queue.push_back(source);
+
+        while let Some(v) = self.queue.pop_front() {
+            if Some(v) == destination {
+                return true;
+            }
+            if !network.expandable(v, source) {
+                continue;
+            }
+            for &(w, _) in network.neighbours(v) {
+                if !self.reached(w) {
+                    self.reach(w, v, 0);
+                    self.queue.push_back(w);
+                }
+            }
+        }
+        destination.is_none()
+    }
+
+    /// Dijkstra over the jump costs of the network's preference.
+    fn cheapest_first(&mut self, network: &Network, source: u32, destination: Option<u32>) -> bool {
+        self.heap.push(Reverse((0, source)));
+
+        while let Some(Reverse((cost, v))) = self.heap.pop() {
+            if Some(v) == destination {
+                return true;
+            }
+            if cost > self.costs[v as usize] || !network.expandable(v, source) {
+                continue;
+            }
+            for &(w, _) in network.neighbours(v) {
+                let next = cost + network.costs[w as usize];
+                if !self.reached(w) || next < self.costs[w as usize] {
+                    self.reach(w, v, next);
+                    self.heap.push(Reverse((next, w)));
+                }
+            }
+        }
+        destination.is_none()
+    }
+
+    /// Jumps to every system from the last search over all of them.
+    fn distances(&self) -> Vec<u16> {
+        (0..self.reached.len() as u32)
+            .map(|index| {
+                if self.reached(index) {
+                    self.jumps[index as usize]
+                } else {
+                    UNREACHABLE
+                }
+            })
+            .collect()
+    }
+
+    fn path(&self, source: u32, destination: u32) -> Vec<u32> {
+        let mut path = vec![destination];
+        let mut node = destination;
+        while node != source {
+            node = self.pred[node as usize];
+            path.push(node);
+        }
+        path.reverse();
+        path
+    }
+}
+
+fn contains(names: &Vec<String>, name: &str) -> bool {
+    let name = normalize(name);
+    names.iter().any(|candidate| normalize(candidate) == name)
+}
+
+fn normalize(name: &str) -> String {
+    name.replace(" ", "").to_uppercase()
+}
+
+/// Route between two systems of `universe` honoring `options`. Where only
+/// the number of jumps matters, `distance` answers without building it.
+pub fn route<'a>(
+    universe: &'a Universe,
+    source: &System,
+    destination: &System,
+    options: &RouteOptions,
+) -> Option<Route<'a>> {
+    let graph = &universe.graph;
+    let from = graph.index(&source.id)?;
+    let to = graph.index(&destination.id)?;
+
+    let (path, hops) = options.with_network(universe, |network| {
+        SEARCH.with(|search| {
+            let mut search = search.borrow_mut();
+            if !network.search(&mut search, from, Some(to)) {
+                return None;
+            }
+            let path = search.path(from, to);
+            let hops = path
+                .windows(2)
+                .map(|pair| network.hop(pair[0], pair[1]))
+                .collect::<Vec<_>>();
+            Some((path, hops))
+        })
+    })?;
+
+    Some(Route {
+        systems: path
+            .iter()
+            .map(|&index| &universe.systems[graph.id(index)])
+            .collect(),
+        distance: hops.len() as u16,
+        hops: hops,
+        destination: &universe.systems[graph.id(to)],
+        source: &universe.systems[graph.id(from)],
+    })
+}
+
+/// Number of jumps on the route between two systems honoring `options`,
+/// without building the route. Answers are cached per options, so asking
+/// again for the same pair does not search.
+pub fn distance(
+    universe: &Universe,
+    source: &System,
+    destination: &System,
+    options: &RouteOptions,
+) -> Option<u16> {
+    let graph = &universe.graph;
+    let from = graph.index(&source.id)?;
+    let to = graph.index(&destination.id)?;
+
+    options.with_network(universe, |network| network.distance(from, to))
+}
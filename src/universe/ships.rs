@@ -0,0 +1,73 @@
+use super::super::errors::*;
+
+/// Kind of ship a ship name or an intel shorthand stands for.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ShipType {
+    pub name: String,
+    /// Group of the type in the static data export, e.g. `Interdictor`.
+    pub class: String,
+    /// Size of the hull, e.g. `Destroyer`.
+    #[serde(default)]
+    pub hull: String,
+}
+
+impl ShipType {
+    /// Reads a list of shorthands intel channels use for ships, one per
+    /// line as `WORD = Class` or `WORD = Class, Hull`, e.g.
+    /// `DIC = Interdictor, Destroyer`. Empty lines and lines starting with
+    /// `#` are ignored.
+    pub fn parse(list: &str) -> Result<Vec<(String, ShipType)>> {
+        let mut shorthands = vec![];
+
+        for (number, line) in list.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("#") {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let word = parts.next().unwrap_or_default().trim();
+            let mut kind = parts
+                .next()
+                .chain_err(|| format!("line {}: expected 'WORD = Class, Hull'", number + 1))?
+                .split(',')
+                .map(|part| part.trim());
+            let class = kind.next().unwrap_or_default();
+            if word.is_empty() || class.is_empty() {
+                bail!("line {}: expected 'WORD = Class, Hull'", number + 1);
+            }
+
+            shorthands.push((
+                word.to_uppercase(),
+                ShipType {
+                    name: capitalize(word),
+                    class: class.to_string(),
+                    hull: kind.next().unwrap_or_default().to_string(),
+                },
+            ));
+        }
+        Ok(shorthands)
+    }
+
+    /// A ship of no particular class, known by its upper cased `name`.
+    pub fn named(name: &str) -> ShipType {
+        ShipType {
+            name: name
+                .split_whitespace()
+                .map(capitalize)
+                .collect::<Vec<_>>()
+                .join(" "),
+            class: String::new(),
+            hull: String::new(),
+        }
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let lower = word.to_lowercase();
+    let mut chars = lower.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,140 @@
+use super::super::errors::*;
+use super::{System, Universe};
+
+use chrono::prelude::*;
+use serde_json;
+use std::fs;
+use std::path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Wormhole {
+    pub from: String,
+    pub to: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Connection {
+    from: String,
+    to: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Temporary connections such as Thera and Turnur holes, usable in both
+/// directions until they expire.
+///
+/// Stored as a JSON list of `{"from", "to", "expires_at"}` objects, system
+/// names and RFC 3339 timestamps, so it can be edited by hand or through
+/// `eintel wormhole`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Wormholes {
+    holes: Vec<Wormhole>,
+    connections: Vec<Connection>,
+}
+
+impl Wormholes {
+    pub fn load(universe: &Universe, path: &path::Path) -> Result<Wormholes> {
+        if !path.exists() {
+            return Ok(Wormholes::default());
+        }
+
+        let contents = fs::read_to_string(path)
+            .chain_err(|| format!("unable to read wormholes from {}", path.display()))?;
+        let holes: Vec<Wormhole> = serde_json::from_str(&contents)
+            .chain_err(|| format!("invalid wormhole list {}", path.display()))?;
+
+        let mut wormholes = Wormholes::default();
+        for hole in holes.into_iter() {
+            let from = universe
+                .find(&hole.from)
+                .chain_err(|| format!("{}: no such system {}", path.display(), hole.from))?;
+            let to = universe
+                .find(&hole.to)
+                .chain_err(|| format!("{}: no such system {}", path.display(), hole.to))?;
+            wormholes.add(from, to, hole.expires_at);
+        }
+        Ok(wormholes)
+    }
+
+    /// Writes the connections that have not expired yet.
+    pub fn save(&self, path: &path::Path) -> Result<()> {
+        let now = Utc::now();
+        let holes = self
+            .holes
+            .iter()
+            .filter(|hole| hole.expires_at > now)
+            .collect::<Vec<_>>();
+
+        fs::write(path, serde_json::to_string_pretty(&holes)?)
+            .chain_err(|| format!("unable to write wormholes to {}", path.display()))
+    }
+
+    pub fn add(&mut self, from: &System, to: &System, expires_at: DateTime<Utc>) {
+        self.remove(from, to);
+        self.holes.push(Wormhole {
+            from: from.name.clone(),
+            to: to.name.clone(),
+            expires_at: expires_at,
+        });
+        self.connections.push(Connection {
+            from: from.id.clone(),
+            to: to.id.clone(),
+            expires_at: expires_at,
+        });
+    }
+
+    pub fn remove(&mut self, from: &System, to: &System) -> bool {
+        let len = self.connections.len();
+        self.holes.retain(|hole| {
+            !(hole.from == from.name && hole.to == to.name
+                || hole.from == to.name && hole.to == from.name)
+        });
+        self.connections
+            .retain(|connection| !connection.joins(&from.id, &to.id));
+        len != self.connections.len()
+    }
+
+    pub fn active(&self) -> Vec<&Wormhole> {
+        let now = Utc::now();
+        self.holes
+            .iter()
+            .filter(|hole| hole.expires_at > now)
+            .collect()
+    }
+
+    pub fn neighbours(&self, id: &str) -> Vec<String> {
+        let now = Utc::now();
+        self.connections
+            .iter()
+            .filter(|connection| connection.expires_at > now)
+            .filter_map(|connection| connection.other(id))
+            .map(|id| id.to_string())
+            .collect()
+    }
+
+    /// When the next active connection collapses.
+    pub fn next_expiry(&self) -> Option<DateTime<Utc>> {
+        let now = Utc::now();
+        self.connections
+            .iter()
+            .map(|connection| connection.expires_at)
+            .filter(|expires_at| *expires_at > now)
+            .min()
+    }
+}
+
+impl Connection {
+    fn joins(&self, from: &str, to: &str) -> bool {
+        self.from == from && self.to == to || self.from == to && self.to == from
+    }
+
+    fn other(&self, id: &str) -> Option<&str> {
+        if self.from == id {
+            Some(&self.to)
+        } else if self.to == id {
+            Some(&self.from)
+        } else {
+            None
+        }
+    }
+}